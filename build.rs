@@ -0,0 +1,128 @@
+//! Compiles the curated vendor table in data/vendors.csv into sorted static tables, one per
+//! block size, so the built-in vendors need no parsing or allocation at runtime
+//! data/test_vendors.csv is compiled the same way, for the vendor.rs tests
+//!
+//! Rows without a category get one from the organization name, with the keywords in
+//! src/registry.rs that also categorize registry files loaded at runtime
+
+#[path = "src/registry.rs"]
+mod registry;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// (source, generated file, static name)
+const TABLES: [(&str, &str, &str); 2] = [
+    ("data/vendors.csv", "builtin_vendors.rs", "BUILTIN"),
+    ("data/test_vendors.csv", "test_vendors.rs", "TEST_VENDORS"),
+];
+
+/// (bits, prefix) -> (name, VendorCategory variant)
+type Entries = BTreeMap<(u32, u64), (String, &'static str)>;
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/registry.rs");
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    for (source, generated, name) in TABLES {
+        println!("cargo:rerun-if-changed={}", source);
+        let entries = read_table(source);
+        let code = format!(
+            "/// Vendors compiled from {}\n\
+             static {}: BuiltinTable = BuiltinTable {{\n\
+             {}{}{}}};\n",
+            source,
+            name,
+            rows(&entries, 24, "ma_l"),
+            rows(&entries, 28, "ma_m"),
+            rows(&entries, 36, "ma_s"),
+        );
+        fs::write(Path::new(&out_dir).join(generated), code).expect("cannot write vendor table");
+    }
+}
+
+/// `prefix,category,name` lines; the name is quoted when it holds commas
+fn read_table(source: &str) -> Entries {
+    let content = fs::read_to_string(source).unwrap_or_else(|e| panic!("cannot read {}: {}", source, e));
+    let mut entries = Entries::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fail = |what: &str| -> ! { panic!("{}:{}: {}: {}", source, number + 1, what, line) };
+
+        let fields = registry::csv_fields(line);
+        let [prefix, category, name] = fields.as_slice() else {
+            fail("expected prefix,category,name");
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            fail("missing name");
+        }
+        let key = parse_prefix(prefix).unwrap_or_else(|| fail("prefix must be 6, 7 or 9 hex digits"));
+        let category = match category.as_str() {
+            "" => registry::category_of(name),
+            category => category,
+        };
+        let Some(variant) = variant(category) else {
+            fail("unknown category");
+        };
+        if entries.insert(key, (name.to_string(), variant)).is_some() {
+            fail("prefix listed twice");
+        }
+    }
+    entries
+}
+
+/// Field `field` of BuiltinTable: the entries with `bits`-bit prefixes, sorted by prefix
+fn rows(entries: &Entries, bits: u32, field: &str) -> String {
+    let mut rows = format!("    {}: &[\n", field);
+    for ((_, prefix), (name, variant)) in entries.range((bits, 0)..(bits + 1, 0)) {
+        // Same rule as VendorInfo::new
+        let short_name = name
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches([',', '.']);
+        writeln!(
+            rows,
+            "        (0x{:X}, VendorInfo {{ name: Cow::Borrowed({:?}), short_name: Cow::Borrowed({:?}), \
+             category: VendorCategory::{}, custom: false }}),",
+            prefix, name, short_name, variant
+        )
+        .unwrap();
+    }
+    rows.push_str("    ],\n");
+    rows
+}
+
+/// Block size in bits and value of a 6, 7 or 9 hex digit prefix
+fn parse_prefix(prefix: &str) -> Option<(u32, u64)> {
+    let bits = match prefix.len() {
+        6 => 24,
+        7 => 28,
+        9 => 36,
+        _ => return None,
+    };
+    Some((bits, u64::from_str_radix(prefix, 16).ok()?))
+}
+
+/// VendorCategory variant of a category name
+fn variant(category: &str) -> Option<&'static str> {
+    Some(match category {
+        "virtualization" => "Virtualization",
+        "networking" => "Networking",
+        "computer" => "Computer",
+        "mobile" => "Mobile",
+        "iot" => "Iot",
+        "locally_administered" => "LocallyAdministered",
+        "randomized" => "Randomized",
+        "other" => "Other",
+        _ => return None,
+    })
+}
@@ -0,0 +1,434 @@
+//! Device type classification from what a device advertises about itself, and the broader
+//! device class that also weighs the vendor, hostname, SSDP details and open ports
+
+use crate::models::{Classification, Device, DeviceClass, DeviceType};
+use crate::ssdp::UpnpInfo;
+use crate::vendor::VendorCategory;
+
+/// DNS-SD service types that identify a kind of device, checked in order
+/// The first rule with a matching service wins, so specific devices come before computers
+const SERVICE_RULES: &[(DeviceType, &[&str])] = &[
+    (
+        DeviceType::Printer,
+        &["_ipp._tcp", "_ipps._tcp", "_printer._tcp", "_pdl-datastream._tcp", "_uscan._tcp"],
+    ),
+    (DeviceType::HomeKitAccessory, &["_hap._tcp", "_hap._udp"]),
+    (
+        DeviceType::SmartTv,
+        &["_androidtvremote._tcp", "_androidtvremote2._tcp", "_amzn-wplay._tcp"],
+    ),
+    (DeviceType::MediaPlayer, &["_googlecast._tcp", "_airplay._tcp"]),
+    (DeviceType::Speaker, &["_raop._tcp", "_spotify-connect._tcp", "_sonos._tcp"]),
+    (DeviceType::Nas, &["_adisk._tcp", "_nfs._tcp"]),
+    (
+        DeviceType::Computer,
+        &["_workstation._tcp", "_ssh._tcp", "_sftp-ssh._tcp", "_rfb._tcp", "_companion-link._tcp", "_smb._tcp"],
+    ),
+];
+
+/// Keywords in an SNMP sysDescr, checked in order (lowercase)
+const SYS_DESCR_RULES: &[(DeviceType, &[&str])] = &[
+    (DeviceType::Printer, &["printer", "jetdirect", "laserjet", "officejet"]),
+    (DeviceType::AccessPoint, &["access point", "unifi ap", "aironet", "wireless"]),
+    (DeviceType::Switch, &["switch"]),
+    (DeviceType::Router, &["router", "routeros", "edgeos", "openwrt", "pfsense"]),
+    (DeviceType::Nas, &["synology", "qnap", "truenas"]),
+];
+
+/// DHCP option 60 vendor class prefixes
+const VENDOR_CLASS_RULES: &[(DeviceType, &str)] = &[
+    (DeviceType::Computer, "MSFT"),
+    (DeviceType::Phone, "android-dhcp"),
+];
+
+/// Well-known option 55 parameter request lists
+const FINGERPRINT_RULES: &[(DeviceType, &str)] = &[
+    // iOS / iPadOS
+    (DeviceType::Phone, "1,121,3,6,15,119,252,95,44,46"),
+    // Android
+    (DeviceType::Phone, "1,3,6,15,26,28,51,58,59,43"),
+    (DeviceType::Phone, "1,3,6,15,26,28,51,58,59,43,114"),
+    // macOS
+    (DeviceType::Computer, "1,121,3,6,15,114,119,252,95,44,46"),
+    (DeviceType::Computer, "1,121,3,6,15,119,252,95,44,46,101"),
+    // Windows 10 / 11
+    (DeviceType::Computer, "1,3,6,15,31,33,43,44,46,47,119,121,249,252"),
+];
+
+/// Points a signal gives to the class it points to; the class with the most points wins
+const SYS_DESCR_POINTS: u32 = 70;
+const SERVICE_POINTS: u32 = 60;
+const UPNP_TYPE_POINTS: u32 = 50;
+const DHCP_POINTS: u32 = 40;
+const HOSTNAME_POINTS: u32 = 40;
+const UPNP_NAME_POINTS: u32 = 30;
+
+/// Keywords in hostnames and UPnP names (lowercase), checked in order
+/// Keywords of up to four letters must be a whole word, optionally followed by a number
+/// ("pve01") or a serial (see HEX_SERIAL_KEYWORDS); longer ones match anywhere in a word
+/// ("fileserver")
+const NAME_RULES: &[(DeviceClass, &[&str])] = &[
+    (DeviceClass::Phone, &["iphone", "android", "galaxy", "pixel", "redmi", "oneplus"]),
+    (
+        DeviceClass::Media,
+        &["tv", "bravia", "chromecast", "roku", "firetv", "appletv", "sonos", "nintendo", "xbox", "playstation"],
+    ),
+    (DeviceClass::Printer, &["printer", "laserjet", "officejet", "deskjet", "epson", "brn", "npi"]),
+    (DeviceClass::Nas, &["nas", "synology", "diskstation", "qnap", "truenas"]),
+    (
+        DeviceClass::Router,
+        &["router", "gateway", "gw", "openwrt", "fritz", "pfsense", "opnsense", "mikrotik"],
+    ),
+    (DeviceClass::SwitchAp, &["switch", "unifi", "ap"]),
+    (
+        DeviceClass::Iot,
+        &["esp", "shelly", "tasmota", "sonoff", "tuya", "hue", "cam", "camera", "doorbell", "thermostat"],
+    ),
+    (DeviceClass::Container, &["lxc", "container"]),
+    (DeviceClass::Server, &["srv", "server", "pve", "proxmox", "esxi", "docker"]),
+    (DeviceClass::Pc, &["desktop", "laptop", "macbook", "imac", "workstation", "thinkpad", "pc"]),
+];
+
+/// Short keywords that vendors follow with a MAC-derived hex serial of fixed length:
+/// HP JetDirect ("NPI3F2A1B") and Brother ("BRN001BA9123456") printers
+const HEX_SERIAL_KEYWORDS: [(&str, usize); 2] = [("npi", 6), ("brn", 12)];
+
+/// Parts of UPnP device type URNs
+const UPNP_TYPE_RULES: &[(DeviceClass, &[&str])] = &[
+    (DeviceClass::Router, &[":InternetGatewayDevice:", ":WANDevice:"]),
+    (DeviceClass::SwitchAp, &[":WLANAccessPointDevice:"]),
+    (DeviceClass::Printer, &[":Printer:", ":Scanner:"]),
+    (DeviceClass::Media, &[":MediaRenderer:", "urn:dial-multiscreen-org:"]),
+];
+
+/// Open TCP ports that point to a class, and their points
+const PORT_RULES: &[(u16, DeviceClass, u32)] = &[
+    (9100, DeviceClass::Printer, 50),
+    (631, DeviceClass::Printer, 30),
+    (515, DeviceClass::Printer, 30),
+    // iOS sync service
+    (62078, DeviceClass::Phone, 60),
+    // Google Cast
+    (8009, DeviceClass::Media, 40),
+    // RTSP, mostly cameras
+    (554, DeviceClass::Iot, 20),
+    // Proxmox VE web UI
+    (8006, DeviceClass::Server, 60),
+    // Synology DSM
+    (5001, DeviceClass::Nas, 20),
+    (53, DeviceClass::Router, 40),
+    (3389, DeviceClass::Pc, 40),
+    (445, DeviceClass::Pc, 15),
+    (22, DeviceClass::Server, 15),
+];
+
+/// Best guess of what a device is, None if nothing it advertises is conclusive
+/// Advertised services are the most specific signal, then SNMP descriptions,
+/// then the DHCP vendor class and fingerprint
+pub fn classify(device: &Device) -> Option<DeviceType> {
+    classify_services(&device.services)
+        .or_else(|| {
+            let descr = device.snmp.as_ref()?.sys_descr.as_deref()?;
+            classify_sys_descr(descr)
+        })
+        .or_else(|| {
+            let client = device.dhcp_client.as_ref()?;
+            classify_dhcp(client.vendor_class.as_deref(), client.fingerprint.as_deref())
+        })
+}
+
+/// Device class from every signal, None if there is none
+/// A hypervisor or container MAC settles it, whatever runs inside. Otherwise each signal
+/// votes for a class; the confidence is the winner's points (capped at 100) scaled by its
+/// share of all points, so disagreeing signals lower it
+pub fn infer_class(device: &Device) -> Option<Classification> {
+    if let Some(class) = virtual_class(device) {
+        return Some(Classification { class, confidence: 100 });
+    }
+
+    let mut votes: Vec<(DeviceClass, u32)> = Vec::new();
+    votes.extend(vendor_vote(device));
+    if let Some(descr) = device.snmp.as_ref().and_then(|s| s.sys_descr.as_deref()) {
+        votes.extend(classify_sys_descr(descr).map(|t| (t.into(), SYS_DESCR_POINTS)));
+    }
+    votes.extend(classify_services(&device.services).map(|t| (t.into(), SERVICE_POINTS)));
+    if let Some(upnp) = &device.upnp {
+        votes.extend(upnp_votes(upnp));
+    }
+    if let Some(client) = &device.dhcp_client {
+        let device_type = classify_dhcp(client.vendor_class.as_deref(), client.fingerprint.as_deref());
+        votes.extend(device_type.map(|t| (t.into(), DHCP_POINTS)));
+    }
+    votes.extend(
+        PORT_RULES
+            .iter()
+            .filter(|(port, _, _)| device.open_ports.contains(port))
+            .map(|(_, class, points)| (*class, *points)),
+    );
+    let hostname = device
+        .hostname
+        .as_deref()
+        .or_else(|| device.dhcp_client.as_ref()?.hostname.as_deref());
+    if let Some(hostname) = hostname {
+        votes.extend(classify_name(hostname).map(|class| (class, HOSTNAME_POINTS)));
+    }
+
+    let total: u32 = votes.iter().map(|(_, points)| points).sum();
+    let mut tally: Vec<(DeviceClass, u32)> = Vec::new();
+    for (class, points) in votes {
+        match tally.iter_mut().find(|(counted, _)| *counted == class) {
+            Some((_, sum)) => *sum += points,
+            None => tally.push((class, points)),
+        }
+    }
+    // On a tie the class voted for first (by the stronger signals) wins
+    let (class, best) = tally.into_iter().reduce(|a, b| if b.1 > a.1 { b } else { a })?;
+    let confidence = best.min(100) * best / total;
+    Some(Classification { class, confidence: confidence as u8 })
+}
+
+/// VM or container by its MAC: a hypervisor prefix, or a guest MAC mapped from Proxmox
+fn virtual_class(device: &Device) -> Option<DeviceClass> {
+    let vendor = device.vendor.as_deref().unwrap_or("").to_lowercase();
+    match device.vendor_category {
+        Some(VendorCategory::Virtualization)
+            if ["container", "docker", "lxc"].iter().any(|k| vendor.contains(k)) =>
+        {
+            Some(DeviceClass::Container)
+        }
+        Some(VendorCategory::Virtualization) => Some(DeviceClass::Vm),
+        _ if device.virtual_mac.is_some() => Some(DeviceClass::Vm),
+        _ => None,
+    }
+}
+
+/// Other vendor categories are weak hints
+fn vendor_vote(device: &Device) -> Option<(DeviceClass, u32)> {
+    match device.vendor_category? {
+        // Bridges and virtual interfaces
+        VendorCategory::LocallyAdministered => Some((DeviceClass::Vm, 40)),
+        VendorCategory::Mobile => Some((DeviceClass::Phone, 30)),
+        VendorCategory::Iot => Some((DeviceClass::Iot, 30)),
+        // Phones rotate private MACs more than anything else
+        VendorCategory::Randomized => Some((DeviceClass::Phone, 20)),
+        VendorCategory::Computer => Some((DeviceClass::Pc, 20)),
+        VendorCategory::Networking => Some((DeviceClass::SwitchAp, 15)),
+        VendorCategory::Virtualization | VendorCategory::Other => None,
+    }
+}
+
+/// Votes from the UPnP device type and the friendly name, model and manufacturer
+fn upnp_votes(upnp: &UpnpInfo) -> Vec<(DeviceClass, u32)> {
+    let by_type = upnp.device_type.as_deref().and_then(|urn| {
+        UPNP_TYPE_RULES
+            .iter()
+            .find(|(_, parts)| parts.iter().any(|part| urn.contains(part)))
+            .map(|(class, _)| (*class, UPNP_TYPE_POINTS))
+    });
+    let names = [&upnp.friendly_name, &upnp.model_name, &upnp.manufacturer];
+    let by_name = names
+        .iter()
+        .filter_map(|name| name.as_deref())
+        .find_map(classify_name)
+        .map(|class| (class, UPNP_NAME_POINTS));
+    by_type.into_iter().chain(by_name).collect()
+}
+
+fn classify_name(name: &str) -> Option<DeviceClass> {
+    let name = name.to_lowercase();
+    let words: Vec<&str> = name.split(|c: char| !c.is_ascii_alphanumeric()).collect();
+    NAME_RULES
+        .iter()
+        .find(|(_, keywords)| {
+            keywords.iter().any(|keyword| {
+                words.iter().any(|word| {
+                    if keyword.len() > 4 {
+                        word.contains(keyword)
+                    } else {
+                        word.strip_prefix(keyword)
+                            .is_some_and(|rest| is_keyword_suffix(keyword, rest))
+                    }
+                })
+            })
+        })
+        .map(|(class, _)| *class)
+}
+
+/// Whether `rest` may follow a short keyword in a word: nothing, a number ("nas1", "ap02") or
+/// the keyword's hex serial. Other letters make it a different word ("nasa", "apac")
+fn is_keyword_suffix(keyword: &str, rest: &str) -> bool {
+    rest.chars().all(|c| c.is_ascii_digit())
+        || HEX_SERIAL_KEYWORDS.iter().any(|&(serial_keyword, len)| {
+            serial_keyword == keyword && rest.len() == len && rest.chars().all(|c| c.is_ascii_hexdigit())
+        })
+}
+
+fn classify_dhcp(vendor_class: Option<&str>, fingerprint: Option<&str>) -> Option<DeviceType> {
+    vendor_class
+        .and_then(|class| {
+            VENDOR_CLASS_RULES
+                .iter()
+                .find(|(_, prefix)| class.starts_with(prefix))
+        })
+        .or_else(|| {
+            let fingerprint = fingerprint?;
+            FINGERPRINT_RULES.iter().find(|(_, known)| *known == fingerprint)
+        })
+        .map(|(device_type, _)| *device_type)
+}
+
+fn classify_sys_descr(descr: &str) -> Option<DeviceType> {
+    let descr = descr.to_lowercase();
+    SYS_DESCR_RULES
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|k| descr.contains(k)))
+        .map(|(device_type, _)| *device_type)
+}
+
+fn classify_services(services: &[String]) -> Option<DeviceType> {
+    // Macs advertise AirPlay receivers too; a sharing service means it isn't a TV or speaker
+    // (Apple TVs also advertise _companion-link, so that one doesn't count here)
+    let is_computer = services.iter().any(|s| {
+        matches!(
+            s.as_str(),
+            "_workstation._tcp" | "_ssh._tcp" | "_sftp-ssh._tcp" | "_rfb._tcp" | "_smb._tcp"
+        )
+    });
+
+    SERVICE_RULES
+        .iter()
+        .filter(|(device_type, _)| {
+            !(is_computer && matches!(device_type, DeviceType::MediaPlayer | DeviceType::Speaker))
+        })
+        .find(|(_, types)| services.iter().any(|s| types.contains(&s.as_str())))
+        .map(|(device_type, _)| *device_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn services(types: &[&str]) -> Vec<String> {
+        types.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_classify_services() {
+        assert_eq!(
+            classify_services(&services(&["_http._tcp", "_ipp._tcp", "_scanner._tcp"])),
+            Some(DeviceType::Printer)
+        );
+        assert_eq!(
+            classify_services(&services(&["_airplay._tcp", "_raop._tcp"])),
+            Some(DeviceType::MediaPlayer)
+        );
+        assert_eq!(
+            classify_services(&services(&["_airplay._tcp", "_companion-link._tcp", "_raop._tcp"])),
+            Some(DeviceType::MediaPlayer)
+        );
+        assert_eq!(
+            classify_services(&services(&["_airplay._tcp", "_raop._tcp", "_smb._tcp"])),
+            Some(DeviceType::Computer)
+        );
+        assert_eq!(classify_services(&services(&["_hap._tcp"])), Some(DeviceType::HomeKitAccessory));
+        assert_eq!(classify_services(&services(&["_http._tcp"])), None);
+    }
+
+    #[test]
+    fn test_classify_sys_descr() {
+        assert_eq!(
+            classify_sys_descr("HP ETHERNET MULTI-ENVIRONMENT,ROM none,JETDIRECT,JD153"),
+            Some(DeviceType::Printer)
+        );
+        assert_eq!(
+            classify_sys_descr("Cisco IOS Software, C2960 Software (C2960-LANBASEK9-M), Catalyst switch"),
+            Some(DeviceType::Switch)
+        );
+        assert_eq!(classify_sys_descr("RouterOS RB4011iGS+"), Some(DeviceType::Router));
+        assert_eq!(classify_sys_descr("Linux nas 5.10.0"), None);
+    }
+
+    #[test]
+    fn test_classify_dhcp() {
+        assert_eq!(classify_dhcp(Some("MSFT 5.0"), None), Some(DeviceType::Computer));
+        assert_eq!(classify_dhcp(Some("android-dhcp-13"), None), Some(DeviceType::Phone));
+        assert_eq!(
+            classify_dhcp(None, Some("1,121,3,6,15,119,252,95,44,46")),
+            Some(DeviceType::Phone)
+        );
+        assert_eq!(classify_dhcp(Some("udhcp 1.36.1"), Some("1,3,6,12,15,28,42")), None);
+    }
+
+    #[test]
+    fn test_classify_name() {
+        assert_eq!(classify_name("Johns-iPhone"), Some(DeviceClass::Phone));
+        assert_eq!(classify_name("DESKTOP-4F2K9QL"), Some(DeviceClass::Pc));
+        assert_eq!(classify_name("NPI3F2A1B"), Some(DeviceClass::Printer));
+        assert_eq!(classify_name("pve01.lan"), Some(DeviceClass::Server));
+        assert_eq!(classify_name("fileserver"), Some(DeviceClass::Server));
+        assert_eq!(classify_name("Living Room TV"), Some(DeviceClass::Media));
+        assert_eq!(classify_name("Nintendo-Switch"), Some(DeviceClass::Media));
+        assert_eq!(classify_name("esp_1a2b3c"), Some(DeviceClass::Iot));
+        // Short keywords don't match inside other words
+        assert_eq!(classify_name("apple"), None);
+        assert_eq!(classify_name("mytvbox"), None);
+
+        assert_eq!(classify_name("nas1"), Some(DeviceClass::Nas));
+        assert_eq!(classify_name("ap02"), Some(DeviceClass::SwitchAp));
+        assert_eq!(classify_name("BRN001BA9123456"), Some(DeviceClass::Printer));
+        // Hex-looking letters after a short keyword make another word
+        for name in ["nasa", "apac", "tvbe", "gwab", "npiabc", "brnab"] {
+            assert_eq!(classify_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_infer_class() {
+        let mut device = Device::new(
+            "00:11:32:aa:bb:cc".parse().unwrap(),
+            Ipv4Addr::new(192, 168, 1, 10),
+            None,
+            None,
+        );
+        assert_eq!(infer_class(&device), None);
+
+        // One weak signal: the hostname
+        device.hostname = Some("diskstation".to_string());
+        let guess = infer_class(&device).unwrap();
+        assert_eq!(guess.class, DeviceClass::Nas);
+        assert_eq!(guess.confidence, 40);
+
+        // Agreeing signals add up
+        device.services = services(&["_adisk._tcp", "_smb._tcp"]);
+        device.open_ports = vec![22, 445, 5001];
+        let guess = infer_class(&device).unwrap();
+        assert_eq!(guess.class, DeviceClass::Nas);
+        assert!(guess.confidence > 40 && guess.confidence < 100);
+
+        // A hypervisor MAC settles it, whatever the guest runs
+        device.vendor_category = Some(VendorCategory::Virtualization);
+        device.vendor = Some("Proxmox Virtual Machine".to_string());
+        assert_eq!(
+            infer_class(&device),
+            Some(Classification { class: DeviceClass::Vm, confidence: 100 })
+        );
+        device.vendor = Some("Docker Container".to_string());
+        assert_eq!(infer_class(&device).unwrap().class, DeviceClass::Container);
+    }
+
+    #[test]
+    fn test_user_class_wins() {
+        let mut device = Device::new(
+            "00:11:32:aa:bb:cc".parse().unwrap(),
+            Ipv4Addr::new(192, 168, 1, 10),
+            Some("Johns-iPhone".to_string()),
+            None,
+        );
+        device.classification = infer_class(&device);
+        assert_eq!(device.class().unwrap().class, DeviceClass::Phone);
+
+        device.user.class = Some(DeviceClass::Iot);
+        assert_eq!(device.class(), Some(Classification { class: DeviceClass::Iot, confidence: 100 }));
+    }
+}
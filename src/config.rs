@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::dhcp::DhcpConfig;
+use crate::dns_sd::DnsSdConfig;
+use crate::hostname_cache::HostnameCacheConfig;
+use crate::oui::VendorConfig;
+use crate::ports::PortsConfig;
+use crate::reverse_dns::DnsConfig;
+use crate::snmp::SnmpConfig;
+use crate::ssdp::SsdpConfig;
+use crate::state::StateConfig;
+use crate::utils::ResolverConfig;
+
+pub const CONFIG_FILE: &str = "getmacrede.json";
+
+/// Optional settings read from getmacrede.json in the working directory
+/// Every section falls back to its defaults when missing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub dhcp: DhcpConfig,
+    pub hostname_cache: HostnameCacheConfig,
+    pub dns: DnsConfig,
+    pub resolver: ResolverConfig,
+    pub ssdp: SsdpConfig,
+    pub dns_sd: DnsSdConfig,
+    pub snmp: SnmpConfig,
+    pub ports: PortsConfig,
+    pub vendor: VendorConfig,
+    pub states: StateConfig,
+}
+
+pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
+    if !Path::new(CONFIG_FILE).exists() {
+        return Ok(Config::default());
+    }
+    let file = File::open(CONFIG_FILE)?;
+    let reader = BufReader::new(file);
+    let config = serde_json::from_reader(reader)?;
+    Ok(config)
+}
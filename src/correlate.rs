@@ -0,0 +1,207 @@
+//! Matching scan results to known devices, independent of their IP address
+//! Phones and laptops rotate locally administered MACs, but keep sending the same DHCP
+//! client identifier, hostname and parameter list, which tie the new MAC to the old device
+
+use std::collections::HashSet;
+
+use crate::mac::MacAddress;
+use crate::models::Device;
+use crate::vendor::VendorCategory;
+
+/// Evidence needed to treat a new MAC as a known device: a client identifier,
+/// or a hostname (a matching fingerprint only supports either)
+/// Hostnames only count for randomized and virtual MACs; a new hardware MAC needs the
+/// client identifier, since default names (`raspberrypi`) and stale lease or DNS names
+/// are shared, and a spoofed name would hide a new device
+const MIN_SCORE: u32 = 2;
+
+/// Known device that `found` is: same MAC, then a mapped, virtual or earlier randomized MAC,
+/// then DHCP client identifier and hostname. A device at the same IP only matches a virtual
+/// or randomized MAC, which change when VMs are recreated or clients rotate their MAC
+/// Devices whose MAC was seen in the same scan (`present`) are still around and only match by MAC
+pub fn find_device<'a>(
+    devices: impl Iterator<Item = &'a Device> + Clone,
+    found: &Device,
+    present: &HashSet<MacAddress>,
+) -> Option<&'a Device> {
+    let found_macs = [Some(found.mac), found.virtual_mac];
+
+    devices
+        .clone()
+        .find(|d| d.mac == found.mac)
+        .or_else(|| devices.clone().find(|d| found_macs.iter().flatten().any(|&mac| d.has_mac(mac))))
+        .or_else(|| find_previous(devices.clone(), found, present))
+        .or_else(|| {
+            devices
+                .clone()
+                .filter(|d| d.ip == found.ip && changing_mac(d) && changing_mac(found) && !present.contains(&d.mac))
+                .max_by_key(|d| d.last_seen)
+        })
+}
+
+/// Give scan results the ID and user details of the known device they are
+pub fn apply_known(devices: &mut [Device], known: &[Device]) {
+    let present: HashSet<MacAddress> = devices.iter().map(|d| d.mac).collect();
+    for device in devices {
+        if let Some(previous) = find_device(known.iter(), device, &present) {
+            device.id = previous.id.clone();
+            device.user = previous.user.clone();
+        }
+    }
+}
+
+/// VM or randomized client MAC
+fn changing_mac(device: &Device) -> bool {
+    device.is_virtual() || device.vendor_category == Some(VendorCategory::Randomized)
+}
+
+/// Known device that `found` most likely is, from its DHCP client identifier and hostname
+fn find_previous<'a>(
+    devices: impl Iterator<Item = &'a Device>,
+    found: &Device,
+    present: &HashSet<MacAddress>,
+) -> Option<&'a Device> {
+    devices
+        .filter(|d| !present.contains(&d.mac))
+        .filter_map(|d| {
+            let score = score(d, found);
+            (score >= MIN_SCORE).then_some((score, d))
+        })
+        .max_by_key(|(score, d)| (*score, d.last_seen))
+        .map(|(_, d)| d)
+}
+
+fn score(known: &Device, found: &Device) -> u32 {
+    let known_client = known.dhcp_client.as_ref();
+    let found_client = found.dhcp_client.as_ref();
+    let mut score = 0;
+
+    if let (Some(a), Some(b)) = (
+        known_client.and_then(|c| c.client_id.as_deref()),
+        found_client.and_then(|c| c.client_id.as_deref()),
+    ) && a == b
+    {
+        score += 3;
+    }
+
+    if let (Some(a), Some(b)) = (&known.hostname, &found.hostname)
+        && changing_mac(found)
+        && a.eq_ignore_ascii_case(b)
+    {
+        score += 2;
+    }
+
+    if let (Some(a), Some(b)) = (
+        known_client.and_then(|c| c.fingerprint.as_deref()),
+        found_client.and_then(|c| c.fingerprint.as_deref()),
+    ) {
+        // A different OS behind the same name is a different device
+        if a != b {
+            return 0;
+        }
+        score += 1;
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dhcp_sniff::DhcpClientInfo;
+
+    fn device(mac: &str, ip: &str, hostname: Option<&str>, client: Option<(&str, &str)>) -> Device {
+        let mut device = Device::new(mac.parse().unwrap(), ip.parse().unwrap(), hostname.map(String::from), None);
+        device.dhcp_client = client.map(|(client_id, fingerprint)| DhcpClientInfo {
+            hostname: None,
+            vendor_class: None,
+            fingerprint: Some(fingerprint.to_string()),
+            client_id: Some(client_id.to_string()),
+        });
+        device
+    }
+
+    #[test]
+    fn test_find_previous() {
+        let phone = device("da:a1:19:00:00:01", "192.168.1.20", Some("Pixel-8"), Some(("01:aa", "1,3,6")));
+        let laptop = device("3a:10:00:00:00:02", "192.168.1.21", Some("laptop"), None);
+        let known = [phone, laptop];
+        let present = HashSet::new();
+
+        // Same client identifier, new MAC and IP
+        let found = device("f2:00:00:00:00:09", "192.168.1.40", None, Some(("01:aa", "1,3,6")));
+        assert_eq!(find_previous(known.iter(), &found, &present).unwrap().ip.to_string(), "192.168.1.20");
+
+        // Hostname alone, for a randomized MAC
+        let mut found = device("f2:00:00:00:00:0a", "192.168.1.41", Some("LAPTOP"), None);
+        found.vendor_category = Some(VendorCategory::Randomized);
+        assert_eq!(find_previous(known.iter(), &found, &present).unwrap().ip.to_string(), "192.168.1.21");
+
+        // Same name, different OS fingerprint
+        let mut found = device("f2:00:00:00:00:0b", "192.168.1.42", Some("Pixel-8"), Some(("01:bb", "1,121,3")));
+        found.vendor_category = Some(VendorCategory::Randomized);
+        assert!(find_previous(known.iter(), &found, &present).is_none());
+
+        // The laptop answered this scan under its own MAC, so it's another device
+        let present = HashSet::from(["3a:10:00:00:00:02".parse().unwrap()]);
+        let mut found = device("f2:00:00:00:00:0a", "192.168.1.41", Some("laptop"), None);
+        found.vendor_category = Some(VendorCategory::Randomized);
+        assert!(find_previous(known.iter(), &found, &present).is_none());
+    }
+
+    #[test]
+    fn test_new_hardware_mac_needs_client_id() {
+        let pi = device("b8:27:eb:00:00:01", "192.168.1.50", Some("raspberrypi"), Some(("01:b8", "1,3,6")));
+        let known = [pi];
+        let present = HashSet::new();
+
+        // A new global MAC with a known (default) hostname is a new device
+        let found = device("dc:a6:32:00:00:02", "192.168.1.51", Some("raspberrypi"), None);
+        assert!(find_device(known.iter(), &found, &present).is_none());
+        let found = device("dc:a6:32:00:00:02", "192.168.1.51", Some("raspberrypi"), Some(("01:dc", "1,3,6")));
+        assert!(find_device(known.iter(), &found, &present).is_none());
+
+        // The same client identifier is the same device
+        let found = device("dc:a6:32:00:00:02", "192.168.1.51", None, Some(("01:b8", "1,3,6")));
+        assert_eq!(find_device(known.iter(), &found, &present).unwrap().id, known[0].id);
+    }
+
+    #[test]
+    fn test_find_device() {
+        let laptop = device("3c:22:fb:00:00:01", "192.168.1.20", Some("laptop"), None);
+        let mut vm = device("bc:24:11:00:00:02", "192.168.1.30", None, None);
+        vm.virtual_mac = Some("fe:00:00:00:00:02".parse().unwrap());
+        let mut phone = device("da:a1:19:00:00:03", "192.168.1.40", None, None);
+        phone.vendor_category = Some(VendorCategory::Randomized);
+        let known = [laptop, vm, phone];
+        let present = HashSet::new();
+
+        // New IP from DHCP, same MAC
+        let found = device("3c:22:fb:00:00:01", "192.168.1.99", None, None);
+        assert_eq!(find_device(known.iter(), &found, &present).unwrap().id, known[0].id);
+
+        // Scanned under its virtual MAC
+        let found = device("fe:00:00:00:00:02", "192.168.1.31", None, None);
+        assert_eq!(find_device(known.iter(), &found, &present).unwrap().id, known[1].id);
+
+        // Another hardware MAC at a known IP is another device
+        let found = device("00:1b:21:00:00:04", "192.168.1.20", None, None);
+        assert!(find_device(known.iter(), &found, &present).is_none());
+
+        // A rotated private MAC at the same IP is the same phone
+        let mut found = device("f2:00:00:00:00:05", "192.168.1.40", None, None);
+        found.vendor_category = Some(VendorCategory::Randomized);
+        assert_eq!(find_device(known.iter(), &found, &present).unwrap().id, known[2].id);
+    }
+
+    #[test]
+    fn test_replace_mac_keeps_aliases() {
+        let mut device = device("da:a1:19:00:00:01", "192.168.1.20", None, None);
+        let rotated: MacAddress = "f2:00:00:00:00:09".parse().unwrap();
+        device.replace_mac(rotated);
+        device.replace_mac("da:a1:19:00:00:01".parse().unwrap());
+        assert_eq!(device.mac.to_string(), "da:a1:19:00:00:01");
+        assert_eq!(device.mac_aliases, vec![rotated]);
+        assert!(device.has_mac(rotated));
+    }
+}
@@ -0,0 +1,695 @@
+//! DHCP server lease files
+//! Supports ISC dhcpd, dnsmasq (also used by Pi-hole and OpenWrt), Kea memfile CSV,
+//! the systemd-networkd DHCP server and OpenWrt's odhcpd
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+
+use crate::models::{Device, HostnameSource};
+use crate::mac::MacAddress;
+use crate::vendor::VendorDb;
+
+/// Lease files probed when none are configured
+const DEFAULT_LEASE_FILES: [&str; 11] = [
+    "/var/lib/dhcp/dhcpd.leases",                 // ISC DHCP Server
+    "/var/lib/dhcpd/dhcpd.leases",                // ISC DHCP Server (alternative)
+    "/tmp/dhcp.leases",                           // dnsmasq (OpenWrt)
+    "/var/lib/misc/dnsmasq.leases",               // dnsmasq (Debian/Ubuntu)
+    "/var/lib/dnsmasq/dnsmasq.leases",            // dnsmasq (alternative)
+    "/etc/pihole/dhcp.leases",                    // Pi-hole
+    "/var/lib/kea/kea-leases4.csv",               // Kea memfile
+    "/var/lib/kea/dhcp4.leases",                  // Kea memfile (alternative)
+    "/tmp/hosts/odhcpd",                          // OpenWrt odhcpd
+    "/var/lib/systemd/network/dhcp-server-lease", // systemd-networkd (directory)
+    "/run/systemd/netif/dhcp-server-lease",       // systemd-networkd runtime (directory)
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaseFormat {
+    /// Detect the format from the file contents
+    #[default]
+    Auto,
+    Isc,
+    Dnsmasq,
+    Kea,
+    Networkd,
+    Odhcpd,
+}
+
+impl LeaseFormat {
+    /// Guess the format of a lease file from its contents
+    pub fn detect(content: &str) -> LeaseFormat {
+        let first = content
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .unwrap_or("");
+
+        if first.starts_with('{') {
+            LeaseFormat::Networkd
+        } else if first.starts_with("address,") {
+            LeaseFormat::Kea
+        } else if first.starts_with("# ")
+            && first.split_whitespace().nth(3).is_some_and(|f| f == "ipv4" || f.len() == 8)
+        {
+            LeaseFormat::Odhcpd
+        } else if content.contains("lease ") && content.contains('{') {
+            LeaseFormat::Isc
+        } else {
+            LeaseFormat::Dnsmasq
+        }
+    }
+}
+
+/// A lease file to read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaseFile {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: LeaseFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DhcpConfig {
+    /// Lease files to read. Common locations are probed when empty
+    pub lease_files: Vec<LeaseFile>,
+    /// Listen for DHCP requests in monitor mode (client hostname, vendor class, fingerprint)
+    pub sniff: bool,
+}
+
+impl Default for DhcpConfig {
+    fn default() -> Self {
+        Self {
+            lease_files: Vec::new(),
+            sniff: true,
+        }
+    }
+}
+
+impl DhcpConfig {
+    pub fn lease_files(&self) -> Vec<LeaseFile> {
+        if !self.lease_files.is_empty() {
+            return self.lease_files.clone();
+        }
+        DEFAULT_LEASE_FILES
+            .iter()
+            .map(|path| LeaseFile {
+                path: PathBuf::from(path),
+                format: LeaseFormat::Auto,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DhcpLease {
+    pub ip: Ipv4Addr,
+    pub mac: Option<MacAddress>,
+    pub hostname: Option<String>,
+    /// None for infinite leases
+    pub expires: Option<DateTime<Utc>>,
+    /// Colon separated hex
+    pub client_id: Option<String>,
+    /// Lease file this entry came from
+    pub source: PathBuf,
+}
+
+impl DhcpLease {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// True when the lease has a MAC and it differs from `mac`
+    pub fn mac_conflicts(&self, mac: MacAddress) -> bool {
+        self.mac.is_some_and(|lease_mac| lease_mac != mac)
+    }
+}
+
+/// Active leases indexed by IP
+#[derive(Debug, Clone, Default)]
+pub struct DhcpLeases {
+    by_ip: HashMap<Ipv4Addr, DhcpLease>,
+}
+
+impl DhcpLeases {
+    pub fn get(&self, ip: &Ipv4Addr) -> Option<&DhcpLease> {
+        self.by_ip.get(ip)
+    }
+
+    /// Hostname for `ip`, ignoring leases that belong to a different MAC than the one seen via ARP
+    pub fn hostname_for(&self, ip: &Ipv4Addr, arp_mac: Option<MacAddress>) -> Option<String> {
+        let lease = self.get(ip)?;
+        if let Some(mac) = arp_mac
+            && lease.mac_conflicts(mac)
+        {
+            return None;
+        }
+        lease.hostname.clone()
+    }
+
+    /// Leases still active at `now`, the last entry per IP deciding
+    /// Ended leases take part until the end, so a release or reclaim written after a
+    /// lease replaces it (ISC appends renewals and frees, Kea appends updates)
+    fn active(leases: impl IntoIterator<Item = DhcpLease>, now: DateTime<Utc>) -> Self {
+        let mut by_ip = HashMap::new();
+        for lease in leases {
+            by_ip.insert(lease.ip, lease);
+        }
+        by_ip.retain(|_, lease: &mut DhcpLease| !lease.is_expired(now));
+        Self { by_ip }
+    }
+}
+
+/// Reconcile a device with the active lease for its IP
+/// When ARP only shows a virtual MAC (bridges and repeaters that rewrite MACs answer ARP
+/// with their own address), the MAC the DHCP server saw replaces it.
+/// With `update_hostname`, the lease hostname is copied when the lease belongs to the device.
+/// Returns true if the device changed
+pub fn apply_lease(
+    device: &mut Device,
+    leases: &DhcpLeases,
+    vendor_db: &VendorDb,
+    update_hostname: bool,
+) -> bool {
+    let Some(lease) = leases.get(&device.ip) else {
+        return false;
+    };
+    let mut changed = false;
+
+    let owned = lease
+        .mac
+        .is_none_or(|lease_mac| lease_mac == device.mac || device.virtual_mac == Some(lease_mac));
+
+    if !owned && let Some(lease_mac) = lease.mac {
+        if !vendor_db.is_virtual(device.mac) || vendor_db.is_virtual(lease_mac) {
+            // The lease belongs to another device (stale lease or IP reuse)
+            return false;
+        }
+        device.virtual_mac = Some(device.mac);
+        device.mac = lease_mac;
+        device.set_vendor(vendor_db.lookup(device.mac));
+        changed = true;
+    }
+
+    if update_hostname && lease.hostname.is_some() && device.hostname != lease.hostname {
+        device.hostname = lease.hostname.clone();
+        device.hostname_source = Some(HostnameSource::Dhcp);
+        device.fcrdns = None;
+        changed = true;
+    }
+
+    changed
+}
+
+/// Load active (non-expired) leases from the configured lease files
+pub fn load_leases(config: &DhcpConfig) -> DhcpLeases {
+    let now = Utc::now();
+    let leases = config
+        .lease_files()
+        .iter()
+        .flat_map(|lease_file| read_lease_path(&lease_file.path, lease_file.format, now))
+        .collect::<Vec<_>>();
+    DhcpLeases::active(leases, now)
+}
+
+/// Read a lease file, or every file in a lease directory (systemd-networkd keeps one per interface)
+fn read_lease_path(path: &Path, format: LeaseFormat, now: DateTime<Utc>) -> Vec<DhcpLease> {
+    if path.is_dir() {
+        let Ok(entries) = fs::read_dir(path) else {
+            return Vec::new();
+        };
+        let mut files: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        files.sort();
+        return files
+            .iter()
+            .filter(|p| p.is_file())
+            .flat_map(|p| read_lease_path(p, format, now))
+            .collect();
+    }
+
+    match fs::read_to_string(path) {
+        Ok(content) => parse_leases(&content, format, path, now),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Parse lease file contents (expired and released leases are kept; callers filter them)
+pub fn parse_leases(
+    content: &str,
+    format: LeaseFormat,
+    source: &Path,
+    now: DateTime<Utc>,
+) -> Vec<DhcpLease> {
+    let format = match format {
+        LeaseFormat::Auto => LeaseFormat::detect(content),
+        other => other,
+    };
+
+    match format {
+        LeaseFormat::Isc => parse_isc(content, source, now),
+        LeaseFormat::Dnsmasq | LeaseFormat::Auto => parse_dnsmasq(content, source),
+        LeaseFormat::Kea => parse_kea(content, source, now),
+        LeaseFormat::Networkd => parse_networkd(content, source),
+        LeaseFormat::Odhcpd => parse_odhcpd(content, source),
+    }
+}
+
+/// dnsmasq: `<expiry epoch> <mac> <ip> <hostname|*> <client-id|*>`
+/// An expiry of 0 means an infinite lease
+fn parse_dnsmasq(content: &str, source: &Path) -> Vec<DhcpLease> {
+    let mut leases = Vec::new();
+
+    for line in content.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 4 {
+            continue;
+        }
+        // IPv6 entries and the "duid" line don't have an IPv4 address here
+        let Ok(ip) = parts[2].parse::<Ipv4Addr>() else {
+            continue;
+        };
+
+        leases.push(DhcpLease {
+            ip,
+            mac: parts[1].parse().ok(),
+            hostname: non_empty(parts[3]),
+            expires: parts[0].parse::<i64>().ok().and_then(epoch_expiry),
+            client_id: parts.get(4).and_then(|id| non_empty(id)).map(|id| id.to_lowercase()),
+            source: source.to_path_buf(),
+        });
+    }
+
+    leases
+}
+
+/// ISC dhcpd: `lease <ip> { ends ...; binding state ...; hardware ethernet ...; uid ...; client-hostname ...; }`
+fn parse_isc(content: &str, source: &Path, now: DateTime<Utc>) -> Vec<DhcpLease> {
+    let mut leases = Vec::new();
+    let mut current: Option<DhcpLease> = None;
+    let mut active = true;
+
+    for line in content.lines() {
+        let trimmed = line.trim().trim_end_matches(';');
+
+        if let Some(rest) = trimmed.strip_prefix("lease ") {
+            current = rest
+                .split_whitespace()
+                .next()
+                .and_then(|ip| ip.parse::<Ipv4Addr>().ok())
+                .map(|ip| DhcpLease {
+                    ip,
+                    mac: None,
+                    hostname: None,
+                    expires: None,
+                    client_id: None,
+                    source: source.to_path_buf(),
+                });
+            active = true;
+            continue;
+        }
+
+        let Some(lease) = current.as_mut() else {
+            continue;
+        };
+
+        if trimmed == "}" {
+            let mut lease = current.take().unwrap();
+            if !active && !lease.is_expired(now) {
+                // Released or freed before its end time
+                lease.expires = Some(now);
+            }
+            leases.push(lease);
+        } else if let Some(ends) = trimmed.strip_prefix("ends ") {
+            lease.expires = parse_isc_time(ends);
+        } else if let Some(state) = trimmed.strip_prefix("binding state ") {
+            active = state.trim() == "active";
+        } else if let Some(mac) = trimmed.strip_prefix("hardware ethernet ") {
+            lease.mac = mac.trim().parse().ok();
+        } else if let Some(uid) = trimmed.strip_prefix("uid ") {
+            lease.client_id = Some(decode_isc_uid(uid.trim()));
+        } else if let Some(name) = trimmed.strip_prefix("client-hostname ") {
+            lease.hostname = non_empty(name.trim().trim_matches('"'));
+        }
+    }
+
+    leases
+}
+
+/// `4 2024/01/01 12:00:00` (UTC), `epoch 1704110400 # ...` or `never`
+fn parse_isc_time(value: &str) -> Option<DateTime<Utc>> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    match parts.as_slice() {
+        ["never", ..] => None,
+        ["epoch", seconds, ..] => seconds.parse::<i64>().ok().and_then(epoch_expiry),
+        [_, date, time, ..] => {
+            NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y/%m/%d %H:%M:%S")
+                .ok()
+                .map(|t| t.and_utc())
+        }
+        _ => None,
+    }
+}
+
+/// ISC writes client identifiers as either hex (`01:aa:bb`) or a quoted string with octal escapes
+fn decode_isc_uid(uid: &str) -> String {
+    let Some(quoted) = uid.strip_prefix('"').and_then(|u| u.strip_suffix('"')) else {
+        return uid.to_lowercase();
+    };
+
+    let mut bytes = Vec::new();
+    let mut chars = quoted.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let digits: String = std::iter::from_fn(|| chars.next_if(|d| d.is_digit(8)))
+                .take(3)
+                .collect();
+            if digits.is_empty() {
+                if let Some(escaped) = chars.next() {
+                    bytes.push(escaped as u8);
+                }
+            } else {
+                bytes.push(u8::from_str_radix(&digits, 8).unwrap_or(0));
+            }
+        } else {
+            bytes.push(c as u8);
+        }
+    }
+
+    to_hex(&bytes)
+}
+
+/// Kea memfile CSV, columns located through the header line
+/// `address,hwaddr,client_id,valid_lifetime,expire,subnet_id,fqdn_fwd,fqdn_rev,hostname,state,...`
+fn parse_kea(content: &str, source: &Path, now: DateTime<Utc>) -> Vec<DhcpLease> {
+    let mut lines = content.lines();
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    let column = |name: &str| columns.iter().position(|c| *c == name);
+    let (Some(address_col), Some(hwaddr_col)) = (column("address"), column("hwaddr")) else {
+        return Vec::new();
+    };
+    let client_id_col = column("client_id");
+    let expire_col = column("expire");
+    let hostname_col = column("hostname");
+    let state_col = column("state");
+
+    let mut leases = Vec::new();
+    for line in lines {
+        let fields: Vec<String> = line
+            .split(',')
+            .map(|f| f.trim().replace("&#x2c", ","))
+            .collect();
+        let field = |col: Option<usize>| col.and_then(|c| fields.get(c)).map(String::as_str);
+
+        let Some(ip) = field(Some(address_col)).and_then(|a| a.parse::<Ipv4Addr>().ok()) else {
+            continue;
+        };
+        // 0 = default (assigned), 1 = declined, 2 = expired-reclaimed
+        let ended = field(state_col).is_some_and(|s| s != "0" && !s.is_empty());
+
+        let mut lease = DhcpLease {
+            ip,
+            mac: field(Some(hwaddr_col)).and_then(|mac| mac.parse().ok()),
+            hostname: field(hostname_col)
+                .map(|h| h.trim_end_matches('.'))
+                .and_then(non_empty),
+            expires: field(expire_col)
+                .and_then(|e| e.parse::<i64>().ok())
+                .and_then(epoch_expiry),
+            client_id: field(client_id_col)
+                .and_then(non_empty)
+                .map(|id| id.to_lowercase()),
+            source: source.to_path_buf(),
+        };
+        if ended && !lease.is_expired(now) {
+            lease.expires = Some(now);
+        }
+        leases.push(lease);
+    }
+
+    leases
+}
+
+/// systemd-networkd DHCP server lease file (JSON, one per interface)
+/// `{"Leases": [{"Address": [192,168,1,10], "HardwareAddress": [...], "ClientId": [...],
+///   "Hostname": "...", "ExpirationRealtimeUSec": ...}]}`
+fn parse_networkd(content: &str, source: &Path) -> Vec<DhcpLease> {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(content) else {
+        return Vec::new();
+    };
+    let Some(entries) = json.get("Leases").and_then(|l| l.as_array()) else {
+        return Vec::new();
+    };
+
+    let bytes = |value: Option<&serde_json::Value>| -> Option<Vec<u8>> {
+        value?
+            .as_array()?
+            .iter()
+            .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+            .collect()
+    };
+
+    let mut leases = Vec::new();
+    for entry in entries {
+        let Some(address) = bytes(entry.get("Address")).filter(|a| a.len() == 4) else {
+            continue;
+        };
+        let client_id = bytes(entry.get("ClientId")).filter(|id| !id.is_empty());
+        // Client IDs of type 1 carry the hardware address
+        let mac = bytes(entry.get("HardwareAddress"))
+            .filter(|hw| hw.len() == 6)
+            .or_else(|| {
+                client_id
+                    .as_ref()
+                    .filter(|id| id.len() == 7 && id[0] == 1)
+                    .map(|id| id[1..].to_vec())
+            })
+            .and_then(|hw| <[u8; 6]>::try_from(hw).ok())
+            .map(MacAddress::new);
+
+        leases.push(DhcpLease {
+            ip: Ipv4Addr::new(address[0], address[1], address[2], address[3]),
+            mac,
+            hostname: entry
+                .get("Hostname")
+                .and_then(|h| h.as_str())
+                .and_then(non_empty),
+            expires: entry
+                .get("ExpirationRealtimeUSec")
+                .and_then(|e| e.as_i64())
+                .and_then(|usec| DateTime::from_timestamp(usec / 1_000_000, 0)),
+            client_id: client_id.map(|id| to_hex(&id)),
+            source: source.to_path_buf(),
+        });
+    }
+
+    leases
+}
+
+/// odhcpd state file: `# <iface> <mac hex> ipv4 <hostname> <valid until> <hostid> 32 <ip>/32`
+/// A validity of -1 means infinite, 0 means expired. Host lines and IPv6 entries are skipped
+fn parse_odhcpd(content: &str, source: &Path) -> Vec<DhcpLease> {
+    let mut leases = Vec::new();
+
+    for line in content.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 9 || parts[0] != "#" || parts[3] != "ipv4" {
+            continue;
+        }
+        let Some(ip) = parts[8]
+            .split('/')
+            .next()
+            .and_then(|a| a.parse::<Ipv4Addr>().ok())
+        else {
+            continue;
+        };
+
+        // Bare hex ("aabbccddeeff")
+        let mac = parts[2].parse().ok();
+
+        let expires = match parts[5].parse::<i64>() {
+            Ok(-1) => None,
+            Ok(seconds) => DateTime::from_timestamp(seconds, 0),
+            Err(_) => None,
+        };
+
+        leases.push(DhcpLease {
+            ip,
+            mac,
+            hostname: non_empty(parts[4]).filter(|h| h != "-"),
+            expires,
+            client_id: None,
+            source: source.to_path_buf(),
+        });
+    }
+
+    leases
+}
+
+/// Expiry from a Unix timestamp where 0 means "never expires"
+fn epoch_expiry(seconds: i64) -> Option<DateTime<Utc>> {
+    if seconds == 0 {
+        None
+    } else {
+        DateTime::from_timestamp(seconds, 0)
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || value == "*" {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_dnsmasq() {
+        let content = "1700003600 AA:BB:CC:DD:EE:FF 192.168.1.100 laptop 01:aa:bb:cc:dd:ee:ff\n\
+                       0 11:22:33:44:55:66 192.168.1.101 * *\n\
+                       duid 00:01:00:01:2c:5e:4f:aa:bb:cc:dd:ee:ff\n";
+        let leases = parse_leases(content, LeaseFormat::Auto, Path::new("dnsmasq.leases"), now());
+
+        assert_eq!(leases.len(), 2);
+        assert_eq!(leases[0].mac, "aa:bb:cc:dd:ee:ff".parse().ok());
+        assert_eq!(leases[0].hostname.as_deref(), Some("laptop"));
+        assert_eq!(leases[0].client_id.as_deref(), Some("01:aa:bb:cc:dd:ee:ff"));
+        assert_eq!(leases[1].hostname, None);
+        assert_eq!(leases[1].expires, None);
+    }
+
+    #[test]
+    fn test_parse_isc() {
+        let content = r#"
+lease 192.168.1.50 {
+  starts 2 2023/11/14 20:00:00;
+  ends 2 2023/11/14 23:00:00;
+  binding state active;
+  hardware ethernet 68:5b:35:8d:89:41;
+  uid "\001h[5\215\211A";
+  client-hostname "desktop";
+}
+lease 192.168.1.51 {
+  ends epoch 1699990000; # expired
+  binding state free;
+  hardware ethernet 68:5b:35:8d:89:42;
+}
+"#;
+        let leases = parse_leases(content, LeaseFormat::Auto, Path::new("dhcpd.leases"), now());
+
+        assert_eq!(leases.len(), 2);
+        assert_eq!(leases[0].hostname.as_deref(), Some("desktop"));
+        assert_eq!(leases[0].client_id.as_deref(), Some("01:68:5b:35:8d:89:41"));
+        assert!(!leases[0].is_expired(now()));
+        assert!(leases[1].is_expired(now()));
+    }
+
+    #[test]
+    fn test_parse_kea() {
+        let content = "address,hwaddr,client_id,valid_lifetime,expire,subnet_id,fqdn_fwd,fqdn_rev,hostname,state,user_context,pool_id\n\
+                       192.168.1.60,aa:bb:cc:00:11:22,01:aa:bb:cc:00:11:22,3600,1700003600,1,0,0,printer.lan.,0,,0\n\
+                       192.168.1.61,aa:bb:cc:00:11:23,,3600,1700003600,1,0,0,,2,,0\n";
+        let leases = parse_leases(content, LeaseFormat::Auto, Path::new("kea-leases4.csv"), now());
+
+        assert_eq!(leases.len(), 2);
+        assert_eq!(leases[0].hostname.as_deref(), Some("printer.lan"));
+        assert_eq!(leases[0].mac, "aa:bb:cc:00:11:22".parse().ok());
+        assert!(!leases[0].is_expired(now()));
+        // Reclaimed
+        assert!(leases[1].is_expired(now()));
+    }
+
+    #[test]
+    fn test_later_free_entry_ends_lease() {
+        let content = r#"
+lease 192.168.1.50 {
+  ends 2 2023/11/14 23:00:00;
+  binding state active;
+  hardware ethernet 68:5b:35:8d:89:41;
+  client-hostname "desktop";
+}
+lease 192.168.1.50 {
+  ends 2 2023/11/14 23:00:00;
+  binding state free;
+  hardware ethernet 68:5b:35:8d:89:41;
+}
+lease 192.168.1.51 {
+  ends 2 2023/11/14 23:00:00;
+  binding state active;
+  hardware ethernet 68:5b:35:8d:89:42;
+}
+"#;
+        let parsed = parse_leases(content, LeaseFormat::Auto, Path::new("dhcpd.leases"), now());
+        let leases = DhcpLeases::active(parsed, now());
+
+        assert!(leases.get(&Ipv4Addr::new(192, 168, 1, 50)).is_none());
+        assert!(leases.get(&Ipv4Addr::new(192, 168, 1, 51)).is_some());
+    }
+
+    #[test]
+    fn test_parse_networkd() {
+        let content = r#"{"Address":[192,168,1,1],"Leases":[{"ClientId":[1,170,187,204,221,238,255],
+            "Address":[192,168,1,70],"Hostname":"nas","ExpirationRealtimeUSec":1700003600000000}]}"#;
+        let leases = parse_leases(content, LeaseFormat::Auto, Path::new("eth0"), now());
+
+        assert_eq!(leases.len(), 1);
+        assert_eq!(leases[0].ip, Ipv4Addr::new(192, 168, 1, 70));
+        assert_eq!(leases[0].mac, "aa:bb:cc:dd:ee:ff".parse().ok());
+        assert_eq!(leases[0].hostname.as_deref(), Some("nas"));
+    }
+
+    #[test]
+    fn test_parse_odhcpd() {
+        let content = "# br-lan aabbccddeeff ipv4 phone 1700003600 64 32 192.168.1.80/32\n\
+                       192.168.1.80\tphone.lan\tphone\n\
+                       # br-lan 000100012c5e4f aabbccdd phone 1700003600 1 128 fd00::80/128\n";
+        let leases = parse_leases(content, LeaseFormat::Auto, Path::new("odhcpd"), now());
+
+        assert_eq!(leases.len(), 1);
+        assert_eq!(leases[0].mac, "aa:bb:cc:dd:ee:ff".parse().ok());
+        assert_eq!(leases[0].hostname.as_deref(), Some("phone"));
+    }
+
+    #[test]
+    fn test_hostname_for_rejects_other_mac() {
+        let lease = DhcpLease {
+            ip: Ipv4Addr::new(192, 168, 1, 100),
+            mac: "aa:bb:cc:dd:ee:ff".parse().ok(),
+            hostname: Some("laptop".to_string()),
+            expires: None,
+            client_id: None,
+            source: PathBuf::from("test"),
+        };
+        let leases = DhcpLeases::active([lease], now());
+        let ip = Ipv4Addr::new(192, 168, 1, 100);
+
+        assert_eq!(leases.hostname_for(&ip, "AA:BB:CC:DD:EE:FF".parse().ok()).as_deref(), Some("laptop"));
+        assert_eq!(leases.hostname_for(&ip, "11:22:33:44:55:66".parse().ok()), None);
+        assert_eq!(leases.hostname_for(&ip, None).as_deref(), Some("laptop"));
+    }
+}
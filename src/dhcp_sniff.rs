@@ -0,0 +1,241 @@
+//! Passive DHCP listener
+//! Clients broadcast DISCOVER/REQUEST packets carrying their hostname (option 12), vendor class
+//! (option 60) and parameter request list (option 55). Together these identify the OS and
+//! device type, even for clients using randomized MACs
+
+use pnet::datalink::{self, Channel};
+use pnet::packet::Packet;
+use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::udp::UdpPacket;
+use serde::{Deserialize, Serialize};
+use std::thread;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::classify;
+use crate::mac::MacAddress;
+use crate::models::{Device, HostnameSource};
+use crate::scanner;
+
+const DHCP_SERVER_PORT: u16 = 67;
+const BOOTREQUEST: u8 = 1;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// Fixed BOOTP header before the magic cookie
+const BOOTP_HEADER_LEN: usize = 236;
+
+const OPTION_PAD: u8 = 0;
+const OPTION_HOSTNAME: u8 = 12;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_PARAMETER_LIST: u8 = 55;
+const OPTION_VENDOR_CLASS: u8 = 60;
+const OPTION_CLIENT_ID: u8 = 61;
+const OPTION_END: u8 = 255;
+
+/// DISCOVER, REQUEST and INFORM carry the client's identity
+const CLIENT_MESSAGE_TYPES: [u8; 3] = [1, 3, 8];
+
+/// What a DHCP client says about itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DhcpClientInfo {
+    /// Option 12
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Option 60 (e.g. "MSFT 5.0", "android-dhcp-13")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor_class: Option<String>,
+    /// Option 55 as comma separated codes, the usual DHCP fingerprint format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// Option 61, colon separated hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
+
+/// Capture DHCP client packets on `interface` (or the default interface)
+/// Sends the client MAC with what the packet revealed
+/// Returns None if the interface can't be opened; capturing needs root like the ARP scan
+pub fn spawn(interface: Option<&str>) -> Option<UnboundedReceiver<(MacAddress, DhcpClientInfo)>> {
+    let interface = match interface {
+        Some(name) => datalink::interfaces().into_iter().find(|iface| iface.name == name),
+        None => scanner::get_default_interface(),
+    }?;
+
+    let mut rx = match datalink::channel(&interface, Default::default()) {
+        Ok(Channel::Ethernet(_, rx)) => rx,
+        Ok(_) => return None,
+        Err(e) => {
+            eprintln!("Warning: cannot listen for DHCP packets on {}: {}", interface.name, e);
+            return None;
+        }
+    };
+
+    let (tx, events) = mpsc::unbounded_channel();
+
+    thread::spawn(move || {
+        loop {
+            let frame = match rx.next() {
+                Ok(frame) => frame,
+                Err(e) => {
+                    eprintln!("Warning: DHCP listener stopped: {}", e);
+                    return;
+                }
+            };
+            let Some(payload) = dhcp_payload(frame) else {
+                continue;
+            };
+            // Receiver dropped: the monitor is gone
+            if let Some(client) = parse_client_packet(&payload)
+                && tx.send(client).is_err()
+            {
+                return;
+            }
+        }
+    });
+
+    Some(events)
+}
+
+/// Record what a client said about itself on its device
+/// Option 12 names the device unless a resolver already did. Returns true if anything changed
+pub fn apply_client(device: &mut Device, info: DhcpClientInfo) -> bool {
+    if device.dhcp_client.as_ref() == Some(&info) {
+        return false;
+    }
+
+    let named_by_resolver = device.hostname.is_some()
+        && device.hostname_source != Some(HostnameSource::Sniffed);
+    if let Some(hostname) = &info.hostname
+        && !named_by_resolver
+    {
+        device.hostname = Some(hostname.clone());
+        device.hostname_source = Some(HostnameSource::Sniffed);
+        device.fcrdns = None;
+    }
+
+    device.dhcp_client = Some(info);
+    if let Some(device_type) = classify::classify(device) {
+        device.device_type = Some(device_type);
+    }
+    device.classification = classify::infer_class(device);
+    true
+}
+
+/// UDP payload of a frame sent to the DHCP server port
+fn dhcp_payload(frame: &[u8]) -> Option<Vec<u8>> {
+    let ethernet = EthernetPacket::new(frame)?;
+    if ethernet.get_ethertype() != EtherTypes::Ipv4 {
+        return None;
+    }
+    let ipv4 = Ipv4Packet::new(ethernet.payload())?;
+    if ipv4.get_next_level_protocol() != IpNextHeaderProtocols::Udp {
+        return None;
+    }
+    let udp = UdpPacket::new(ipv4.payload())?;
+    if udp.get_destination() != DHCP_SERVER_PORT {
+        return None;
+    }
+    Some(udp.payload().to_vec())
+}
+
+/// Parse a BOOTREQUEST, returning the client hardware address and its identity options
+fn parse_client_packet(buf: &[u8]) -> Option<(MacAddress, DhcpClientInfo)> {
+    // Ethernet clients only: htype 1, hlen 6
+    if *buf.first()? != BOOTREQUEST || buf.get(1..3)? != [1, 6] {
+        return None;
+    }
+    if buf.get(BOOTP_HEADER_LEN..BOOTP_HEADER_LEN + 4)? != MAGIC_COOKIE {
+        return None;
+    }
+    let mac = MacAddress::new(buf.get(28..34)?.try_into().ok()?);
+
+    let mut info = DhcpClientInfo {
+        hostname: None,
+        vendor_class: None,
+        fingerprint: None,
+        client_id: None,
+    };
+    let mut message_type = None;
+
+    let mut pos = BOOTP_HEADER_LEN + 4;
+    while let Some(&code) = buf.get(pos) {
+        match code {
+            OPTION_PAD => {
+                pos += 1;
+                continue;
+            }
+            OPTION_END => break,
+            _ => {}
+        }
+        let len = *buf.get(pos + 1)? as usize;
+        let data = buf.get(pos + 2..pos + 2 + len)?;
+        match code {
+            OPTION_HOSTNAME => info.hostname = text(data),
+            OPTION_MESSAGE_TYPE => message_type = data.first().copied(),
+            OPTION_PARAMETER_LIST => {
+                info.fingerprint = Some(
+                    data.iter()
+                        .map(|code| code.to_string())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                )
+            }
+            OPTION_VENDOR_CLASS => info.vendor_class = text(data),
+            OPTION_CLIENT_ID if !data.is_empty() => info.client_id = Some(hex(data)),
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+
+    if !CLIENT_MESSAGE_TYPES.contains(&message_type?) {
+        return None;
+    }
+    Some((mac, info))
+}
+
+fn text(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data)
+        .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .to_string();
+    if text.is_empty() { None } else { Some(text) }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let mut packet = vec![0u8; BOOTP_HEADER_LEN];
+        packet[0] = BOOTREQUEST;
+        packet[1] = 1;
+        packet[2] = 6;
+        packet[28..34].copy_from_slice(&[0xa4, 0x83, 0xe7, 0x12, 0x34, 0x56]);
+        packet.extend(MAGIC_COOKIE);
+        packet.extend([OPTION_MESSAGE_TYPE, 1, 3]);
+        packet.extend([OPTION_CLIENT_ID, 7, 1, 0xa4, 0x83, 0xe7, 0x12, 0x34, 0x56]);
+        packet.extend([OPTION_HOSTNAME, 6]);
+        packet.extend(b"iPhone");
+        packet.extend([OPTION_PARAMETER_LIST, 4, 1, 121, 3, 6]);
+        packet.extend([OPTION_PAD, OPTION_END]);
+
+        let (mac, info) = parse_client_packet(&packet).unwrap();
+        assert_eq!(mac.to_string(), "a4:83:e7:12:34:56");
+        assert_eq!(info.hostname.as_deref(), Some("iPhone"));
+        assert_eq!(info.fingerprint.as_deref(), Some("1,121,3,6"));
+        assert_eq!(info.client_id.as_deref(), Some("01:a4:83:e7:12:34:56"));
+        assert_eq!(info.vendor_class, None);
+
+        // Server replies are ignored
+        packet[0] = 2;
+        assert!(parse_client_packet(&packet).is_none());
+    }
+}
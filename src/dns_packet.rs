@@ -0,0 +1,227 @@
+//! Minimal DNS wire format support (RFC 1035)
+//! Shared by the multicast resolvers that can't go through the system resolver
+
+use std::net::Ipv4Addr;
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_PTR: u16 = 12;
+pub const CLASS_IN: u16 = 1;
+
+/// Top bit of QCLASS asks an mDNS responder to reply by unicast (RFC 6762, section 5.4)
+pub const CLASS_UNICAST_RESPONSE: u16 = 0x8000;
+
+/// Maximum number of compression pointers followed while decoding a single name
+const MAX_POINTER_JUMPS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Ptr(String),
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub rtype: u16,
+    pub data: RecordData,
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub id: u16,
+    pub flags: u16,
+    /// Answer, authority and additional records, in wire order
+    pub records: Vec<Record>,
+}
+
+impl Message {
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+}
+
+/// Builds the reverse lookup name for an IPv4 address
+/// Example: 192.168.1.100 -> 100.1.168.192.in-addr.arpa
+pub fn reverse_name(ip: Ipv4Addr) -> String {
+    let o = ip.octets();
+    format!("{}.{}.{}.{}.in-addr.arpa", o[3], o[2], o[1], o[0])
+}
+
+/// Builds a single-question query packet
+pub fn build_query(id: u16, name: &str, qtype: u16, qclass: u16) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(12 + name.len() + 6);
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&0u16.to_be_bytes()); // flags: standard query
+    buf.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    buf.extend_from_slice(&0u16.to_be_bytes()); // ANCOUNT
+    buf.extend_from_slice(&0u16.to_be_bytes()); // NSCOUNT
+    buf.extend_from_slice(&0u16.to_be_bytes()); // ARCOUNT
+    encode_name(&mut buf, name);
+    buf.extend_from_slice(&qtype.to_be_bytes());
+    buf.extend_from_slice(&qclass.to_be_bytes());
+    buf
+}
+
+fn encode_name(buf: &mut Vec<u8>, name: &str) {
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() {
+            continue;
+        }
+        let bytes = label.as_bytes();
+        let len = bytes.len().min(63);
+        buf.push(len as u8);
+        buf.extend_from_slice(&bytes[..len]);
+    }
+    buf.push(0);
+}
+
+/// Parses a DNS message, returning None if it is truncated or malformed
+pub fn parse_message(buf: &[u8]) -> Option<Message> {
+    if buf.len() < 12 {
+        return None;
+    }
+
+    let id = read_u16(buf, 0)?;
+    let flags = read_u16(buf, 2)?;
+    let qdcount = read_u16(buf, 4)? as usize;
+    let record_count =
+        read_u16(buf, 6)? as usize + read_u16(buf, 8)? as usize + read_u16(buf, 10)? as usize;
+
+    let mut pos = 12;
+
+    // Skip the question section
+    for _ in 0..qdcount {
+        let (_, next) = read_name(buf, pos)?;
+        pos = next + 4;
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..record_count {
+        let (name, next) = read_name(buf, pos)?;
+        let rtype = read_u16(buf, next)?;
+        let rdlength = read_u16(buf, next + 8)? as usize;
+        let rdata_start = next + 10;
+        let rdata_end = rdata_start + rdlength;
+        if rdata_end > buf.len() {
+            return None;
+        }
+
+        let data = match rtype {
+            TYPE_A if rdlength == 4 => RecordData::A(Ipv4Addr::new(
+                buf[rdata_start],
+                buf[rdata_start + 1],
+                buf[rdata_start + 2],
+                buf[rdata_start + 3],
+            )),
+            TYPE_PTR => RecordData::Ptr(read_name(buf, rdata_start)?.0),
+            _ => RecordData::Other,
+        };
+
+        records.push(Record { name, rtype, data });
+        pos = rdata_end;
+    }
+
+    Some(Message { id, flags, records })
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Option<u16> {
+    let bytes = buf.get(pos..pos + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads a (possibly compressed) name starting at `pos`
+/// Returns the dotted name and the position right after it in the original buffer
+fn read_name(buf: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end: Option<usize> = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *buf.get(pos)? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+        if len & 0xC0 == 0xC0 {
+            // Compression pointer: the remaining labels live at the given offset
+            let offset = (read_u16(buf, pos)? & 0x3FFF) as usize;
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            jumps += 1;
+            if jumps > MAX_POINTER_JUMPS {
+                return None;
+            }
+            pos = offset;
+            continue;
+        }
+        let label = buf.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len;
+    }
+
+    Some((labels.join("."), end.unwrap_or(pos)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse_name() {
+        assert_eq!(
+            reverse_name(Ipv4Addr::new(192, 168, 1, 100)),
+            "100.1.168.192.in-addr.arpa"
+        );
+    }
+
+    #[test]
+    fn test_parse_ptr_answer() {
+        let mut packet = build_query(0, "100.1.168.192.in-addr.arpa", TYPE_PTR, CLASS_IN);
+        packet[2] = 0x84; // response, authoritative
+        packet[5] = 0; // no question
+        packet[7] = 1; // one answer
+        // Replace the question by an answer whose owner name is the old question name
+        let name_len = packet.len() - 12 - 4;
+        packet.truncate(12 + name_len);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet.extend_from_slice(&120u32.to_be_bytes());
+        let mut rdata = Vec::new();
+        rdata.extend_from_slice(&[7]);
+        rdata.extend_from_slice(b"macbook");
+        rdata.extend_from_slice(&[5]);
+        rdata.extend_from_slice(b"local");
+        rdata.push(0);
+        packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&rdata);
+
+        let message = parse_message(&packet).unwrap();
+        assert!(message.is_response());
+        assert_eq!(message.records.len(), 1);
+        assert_eq!(message.records[0].name, "100.1.168.192.in-addr.arpa");
+        assert_eq!(
+            message.records[0].data,
+            RecordData::Ptr("macbook.local".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read_name_follows_pointer() {
+        // "local" at offset 0, then "macbook" + pointer to offset 0
+        let buf = [
+            5, b'l', b'o', b'c', b'a', b'l', 0, 7, b'm', b'a', b'c', b'b', b'o', b'o', b'k', 0xC0,
+            0x00,
+        ];
+        let (name, next) = read_name(&buf, 7).unwrap();
+        assert_eq!(name, "macbook.local");
+        assert_eq!(next, buf.len());
+    }
+
+    #[test]
+    fn test_pointer_loop_is_rejected() {
+        let buf = [0xC0, 0x00];
+        assert!(read_name(&buf, 0).is_none());
+    }
+}
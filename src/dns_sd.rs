@@ -0,0 +1,144 @@
+//! DNS-SD service browsing over multicast DNS (RFC 6763)
+//! Advertised service types (`_ipp._tcp`, `_airplay._tcp`, `_hap._tcp`, ...) say what a device is
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout_at, Instant};
+
+use crate::dns_packet::{self, RecordData, CLASS_IN, CLASS_UNICAST_RESPONSE, TYPE_PTR};
+use crate::mdns::{MDNS_ADDR, MDNS_PORT};
+
+/// Meta-query listing every service type advertised on the link
+const SERVICE_ENUMERATION: &str = "_services._dns-sd._udp.local";
+
+/// Browsed directly as well, some responders don't answer the enumeration query
+const KNOWN_SERVICES: [&str; 14] = [
+    "_airplay._tcp.local",
+    "_raop._tcp.local",
+    "_googlecast._tcp.local",
+    "_hap._tcp.local",
+    "_ipp._tcp.local",
+    "_ipps._tcp.local",
+    "_printer._tcp.local",
+    "_pdl-datastream._tcp.local",
+    "_ssh._tcp.local",
+    "_smb._tcp.local",
+    "_workstation._tcp.local",
+    "_spotify-connect._tcp.local",
+    "_androidtvremote2._tcp.local",
+    "_companion-link._tcp.local",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsSdConfig {
+    pub enabled: bool,
+    /// How long each scan listens for mDNS answers, in milliseconds
+    pub listen_window_ms: u64,
+}
+
+impl Default for DnsSdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            listen_window_ms: 1500,
+        }
+    }
+}
+
+/// Browse the link and return the advertised service types per responder IP,
+/// e.g. 192.168.1.30 -> ["_airplay._tcp", "_raop._tcp"]
+pub async fn browse(listen_window: Duration) -> HashMap<Ipv4Addr, Vec<String>> {
+    match browse_services(listen_window).await {
+        Ok(services) => services
+            .into_iter()
+            .map(|(ip, types)| (ip, types.into_iter().collect()))
+            .collect(),
+        Err(e) => {
+            eprintln!("Warning: DNS-SD browsing failed: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+async fn browse_services(
+    listen_window: Duration,
+) -> std::io::Result<HashMap<Ipv4Addr, BTreeSet<String>>> {
+    let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)).await?;
+    // Link-local traffic: responders must discard packets whose TTL isn't 255
+    socket.set_multicast_ttl_v4(255)?;
+
+    // Queries from an ephemeral port with the unicast-response bit are answered directly,
+    // so every answer's source address is the device advertising the service
+    let destination = SocketAddrV4::new(MDNS_ADDR, MDNS_PORT);
+    for name in std::iter::once(SERVICE_ENUMERATION).chain(KNOWN_SERVICES) {
+        let query = dns_packet::build_query(0, name, TYPE_PTR, CLASS_IN | CLASS_UNICAST_RESPONSE);
+        socket.send_to(&query, destination).await?;
+    }
+
+    let deadline = Instant::now() + listen_window;
+    let mut buf = [0u8; 9000];
+    let mut services: HashMap<Ipv4Addr, BTreeSet<String>> = HashMap::new();
+
+    while let Ok(received) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, SocketAddr::V4(from)) = received? else {
+            continue;
+        };
+        let Some(message) = dns_packet::parse_message(&buf[..len]) else {
+            continue;
+        };
+        if !message.is_response() {
+            continue;
+        }
+
+        for record in message.records {
+            // Enumeration answers name the type in the PTR target, every other record
+            // (PTR, SRV, TXT) is owned by the type or one of its instances
+            let service = if record.name.eq_ignore_ascii_case(SERVICE_ENUMERATION) {
+                match &record.data {
+                    RecordData::Ptr(target) => service_type(target),
+                    _ => None,
+                }
+            } else {
+                service_type(&record.name)
+            };
+            if let Some(service) = service {
+                services.entry(*from.ip()).or_default().insert(service);
+            }
+        }
+    }
+
+    Ok(services)
+}
+
+/// Service type of a DNS-SD name, e.g. "Living Room._airplay._tcp.local" -> "_airplay._tcp"
+/// Subtypes ("_printer._sub._ipp._tcp.local") resolve to their parent type
+fn service_type(name: &str) -> Option<String> {
+    let labels: Vec<&str> = name.trim_end_matches('.').split('.').collect();
+    let proto = labels
+        .iter()
+        .rposition(|label| label.eq_ignore_ascii_case("_tcp") || label.eq_ignore_ascii_case("_udp"))?;
+    let service = labels.get(proto.checked_sub(1)?)?;
+    if !service.starts_with('_') || service.eq_ignore_ascii_case("_dns-sd") {
+        return None;
+    }
+    Some(format!("{}.{}", service, labels[proto]).to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_type() {
+        assert_eq!(service_type("Living Room._airplay._tcp.local").as_deref(), Some("_airplay._tcp"));
+        assert_eq!(service_type("_googlecast._tcp.local.").as_deref(), Some("_googlecast._tcp"));
+        assert_eq!(service_type("_printer._sub._IPP._tcp.local").as_deref(), Some("_ipp._tcp"));
+        assert_eq!(service_type("Bridge._hap._udp.local").as_deref(), Some("_hap._udp"));
+        assert_eq!(service_type(SERVICE_ENUMERATION), None);
+        assert_eq!(service_type("MacBook-Pro.local"), None);
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::{DateTime, Duration, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use crate::mac::MacAddress;
+use crate::models::{HostnameAnswer, HostnameSource};
+use crate::netbios::NodeStatus;
+
+/// Stored next to devices.json
+const HOSTNAME_CACHE_FILE: &str = "hostnames.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostnameCacheConfig {
+    pub enabled: bool,
+    /// How long a resolved name is reused, in seconds
+    pub positive_ttl: i64,
+    /// How long a failed resolution is remembered, in seconds
+    pub negative_ttl: i64,
+}
+
+impl Default for HostnameCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            positive_ttl: 3600,
+            negative_ttl: 300,
+        }
+    }
+}
+
+/// A cached resolution result for one MAC address
+/// Entries with source `manual` are user-pinned names and never expire
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<HostnameSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fcrdns: Option<bool>,
+    /// Names from the other resolvers, in "all" resolver mode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub others: Vec<HostnameAnswer>,
+    /// NetBIOS workgroup and reported MAC, when NetBIOS answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub netbios: Option<NodeStatus>,
+    pub resolved_at: DateTime<Utc>,
+}
+
+pub enum CacheLookup<'a> {
+    /// A name (or a remembered failure, with `hostname: None`) that is still fresh
+    Hit(&'a CacheEntry),
+    Miss,
+}
+
+/// Hostname resolution results keyed by MAC address
+#[derive(Debug, Default)]
+pub struct HostnameCache {
+    entries: HashMap<String, CacheEntry>,
+    dirty: bool,
+}
+
+impl HostnameCache {
+    /// Load the cache file, starting empty if it's missing or unreadable
+    pub fn load() -> Self {
+        match load_entries() {
+            Ok(entries) => Self {
+                entries,
+                dirty: false,
+            },
+            Err(e) => {
+                eprintln!("Warning: ignoring {}: {}", HOSTNAME_CACHE_FILE, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn lookup(&self, mac: MacAddress, config: &HostnameCacheConfig, now: DateTime<Utc>) -> CacheLookup<'_> {
+        let Some(entry) = self.entries.get(&mac.to_string()) else {
+            return CacheLookup::Miss;
+        };
+        if entry.source == Some(HostnameSource::Manual) {
+            return CacheLookup::Hit(entry);
+        }
+
+        let ttl = if entry.hostname.is_some() {
+            config.positive_ttl
+        } else {
+            config.negative_ttl
+        };
+        if now.signed_duration_since(entry.resolved_at) < Duration::seconds(ttl) {
+            CacheLookup::Hit(entry)
+        } else {
+            CacheLookup::Miss
+        }
+    }
+
+    /// Record a resolution result (empty for "no name found")
+    /// Manual entries are never overwritten
+    pub fn insert(&mut self, mac: MacAddress, answers: &[HostnameAnswer], now: DateTime<Utc>) {
+        let key = mac.to_string();
+        if self
+            .entries
+            .get(&key)
+            .is_some_and(|e| e.source == Some(HostnameSource::Manual))
+        {
+            return;
+        }
+
+        let primary = answers.first();
+        self.entries.insert(
+            key,
+            CacheEntry {
+                hostname: primary.map(|a| a.hostname.clone()),
+                source: primary.map(|a| a.source),
+                fcrdns: primary.and_then(|a| a.fcrdns),
+                others: answers.iter().skip(1).cloned().collect(),
+                netbios: answers.iter().find_map(|a| a.netbios.clone()),
+                resolved_at: now,
+            },
+        );
+        self.dirty = true;
+    }
+
+    /// Write the cache back if anything changed
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let file = File::create(HOSTNAME_CACHE_FILE)?;
+        file.lock_exclusive()?;
+        let writer = BufWriter::new(&file);
+        serde_json::to_writer_pretty(writer, &self.entries)?;
+        file.unlock().ok();
+
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+fn load_entries() -> io::Result<HashMap<String, CacheEntry>> {
+    if !Path::new(HOSTNAME_CACHE_FILE).exists() {
+        return Ok(HashMap::new());
+    }
+
+    let file = File::open(HOSTNAME_CACHE_FILE)?;
+    file.lock_shared()?;
+    let reader = BufReader::new(&file);
+    let entries: HashMap<String, CacheEntry> = serde_json::from_reader(reader)?;
+    file.unlock().ok();
+
+    // Keys are matched in canonical form, hand-written entries may use any MAC notation
+    Ok(entries
+        .into_iter()
+        .map(|(mac, entry)| match mac.parse::<MacAddress>() {
+            Ok(parsed) => (parsed.to_string(), entry),
+            Err(_) => (mac, entry),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positive_and_negative_ttl() {
+        let config = HostnameCacheConfig::default();
+        let now = Utc::now();
+        let mut cache = HostnameCache::default();
+
+        let answer = HostnameAnswer::new("laptop".to_string(), HostnameSource::Dns);
+        cache.insert("AA:BB:CC:DD:EE:FF".parse().unwrap(), &[answer], now);
+        cache.insert("11:22:33:44:55:66".parse().unwrap(), &[], now);
+
+        let later = now + Duration::seconds(config.negative_ttl + 1);
+        assert!(matches!(
+            cache.lookup("aa:bb:cc:dd:ee:ff".parse().unwrap(), &config, later),
+            CacheLookup::Hit(entry) if entry.hostname.as_deref() == Some("laptop")
+        ));
+        assert!(matches!(cache.lookup("11:22:33:44:55:66".parse().unwrap(), &config, later), CacheLookup::Miss));
+
+        let much_later = now + Duration::seconds(config.positive_ttl + 1);
+        assert!(matches!(cache.lookup("aa:bb:cc:dd:ee:ff".parse().unwrap(), &config, much_later), CacheLookup::Miss));
+    }
+
+    #[test]
+    fn test_manual_entries_are_pinned() {
+        let config = HostnameCacheConfig::default();
+        let long_ago = Utc::now() - Duration::days(365);
+        let mut cache = HostnameCache::default();
+        cache.entries.insert(
+            "aa:bb:cc:dd:ee:ff".to_string(),
+            CacheEntry {
+                hostname: Some("nas".to_string()),
+                source: Some(HostnameSource::Manual),
+                fcrdns: None,
+                others: Vec::new(),
+                netbios: None,
+                resolved_at: long_ago,
+            },
+        );
+
+        let answer = HostnameAnswer::new("other".to_string(), HostnameSource::Dns);
+        cache.insert("aa:bb:cc:dd:ee:ff".parse().unwrap(), &[answer], Utc::now());
+        assert!(matches!(
+            cache.lookup("aa:bb:cc:dd:ee:ff".parse().unwrap(), &config, Utc::now()),
+            CacheLookup::Hit(entry) if entry.hostname.as_deref() == Some("nas")
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+//! Watches DHCP lease files with inotify so the monitor can pick up new leases between scans
+
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::Path;
+use std::thread;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::dhcp::LeaseFile;
+
+/// Start watching the lease files on this machine, including ones a server has yet to create
+/// Each change sends a unit message; callers should debounce, servers often write in bursts
+/// Returns None if no lease file could be watched
+pub fn spawn(lease_files: &[LeaseFile]) -> Option<UnboundedReceiver<()>> {
+    let mut inotify = Inotify::init().ok()?;
+
+    // Parent directories are watched instead of the files themselves, so files replaced
+    // by rename (ISC dhcpd), recreated after a server restart or not written yet keep being
+    // tracked. dnsmasq keeps its lease file open, so plain modifications count too
+    let mask = WatchMask::MODIFY
+        | WatchMask::CLOSE_WRITE
+        | WatchMask::MOVED_TO
+        | WatchMask::CREATE
+        | WatchMask::DELETE;
+
+    // Watch -> file name filter (None watches every file of a lease directory)
+    let mut filters: HashMap<WatchDescriptor, Vec<Option<OsString>>> = HashMap::new();
+
+    for lease_file in lease_files {
+        let Some((dir, name)) = watch_target(&lease_file.path) else {
+            continue;
+        };
+
+        match inotify.watches().add(dir, mask) {
+            Ok(wd) => filters.entry(wd).or_default().push(name),
+            Err(e) => eprintln!("Warning: cannot watch {}: {}", dir.display(), e),
+        }
+    }
+
+    if filters.is_empty() {
+        return None;
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; 4096];
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("Warning: lease file watcher stopped: {}", e);
+                    return;
+                }
+            };
+
+            let relevant = events.into_iter().any(|event| {
+                filters.get(&event.wd).is_some_and(|names| {
+                    names.iter().any(|filter| match filter {
+                        Some(name) => event.name == Some(name.as_os_str()),
+                        None => true,
+                    })
+                })
+            });
+
+            // Receiver dropped: the monitor is gone
+            if relevant && tx.send(()).is_err() {
+                return;
+            }
+        }
+    });
+
+    Some(rx)
+}
+
+/// Directory to watch for a lease path and the file name to filter its events by
+/// Lease directories are watched whole; files, present or not, through their parent directory
+/// None when not even the parent directory exists
+fn watch_target(path: &Path) -> Option<(&Path, Option<OsString>)> {
+    if path.is_dir() {
+        return Some((path, None));
+    }
+    let dir = path.parent().filter(|dir| dir.is_dir())?;
+    Some((dir, Some(path.file_name()?.to_os_string())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_target() {
+        let dir = std::env::temp_dir().join(format!("getmacrede-leases-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("dnsmasq.leases");
+        std::fs::write(&file, "").unwrap();
+        let missing = dir.join("dhcpd.leases");
+        let missing_dir = dir.join("kea").join("kea-leases4.csv");
+
+        let targets = [&file, &dir, &missing, &missing_dir].map(|path| watch_target(path));
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(targets[0], Some((dir.as_path(), Some("dnsmasq.leases".into()))));
+        assert_eq!(targets[1], Some((dir.as_path(), None)));
+        // Not created yet: its directory is watched for that name
+        assert_eq!(targets[2], Some((dir.as_path(), Some("dhcpd.leases".into()))));
+        assert_eq!(targets[3], None);
+    }
+}
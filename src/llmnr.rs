@@ -0,0 +1,136 @@
+//! Link-Local Multicast Name Resolution (RFC 4795) reverse lookups
+//! Windows 10/11 hosts answer LLMNR even when NetBIOS over TCP/IP is disabled
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+use crate::dns_packet::{self, RecordData, CLASS_IN, TYPE_PTR};
+
+pub const LLMNR_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 252);
+pub const LLMNR_PORT: u16 = 5355;
+
+/// How long to wait for an LLMNR response
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(700);
+
+/// Ask for the name owning `ip` via LLMNR
+/// The query goes both to the multicast group and straight to the host,
+/// since some responders only answer reverse queries sent to their own address
+pub async fn reverse_lookup(ip: Ipv4Addr, wait: Duration) -> Option<String> {
+    let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .ok()?;
+    // LLMNR multicast must not leave the link
+    socket.set_multicast_ttl_v4(1).ok()?;
+
+    let (id, query_name, query) = reverse_query(ip);
+
+    socket
+        .send_to(&query, SocketAddrV4::new(LLMNR_ADDR, LLMNR_PORT))
+        .await
+        .ok()?;
+    // A failed unicast send is fine as long as the multicast one went out
+    let _ = socket
+        .send_to(&query, SocketAddrV4::new(ip, LLMNR_PORT))
+        .await;
+
+    let mut buf = [0u8; 1500];
+    let result = timeout(wait, async {
+        loop {
+            let (len, _) = socket.recv_from(&mut buf).await.ok()?;
+            if let Some(name) = parse_reverse_answer(&buf[..len], id, &query_name) {
+                return Some(name);
+            }
+        }
+    })
+    .await;
+
+    result.ok().flatten()
+}
+
+/// Query id, queried name and packet of the PTR query for `ip`
+/// The id is derived from the address, so answers to other queries are told apart
+fn reverse_query(ip: Ipv4Addr) -> (u16, String, Vec<u8>) {
+    let query_name = dns_packet::reverse_name(ip);
+    let octets = ip.octets();
+    let id = u16::from_be_bytes([octets[2], octets[3]]) ^ 0x4c4c;
+    let query = dns_packet::build_query(id, &query_name, TYPE_PTR, CLASS_IN);
+    (id, query_name, query)
+}
+
+/// Name from a PTR answer to query `id` for `query_name`, None for anything else
+fn parse_reverse_answer(packet: &[u8], id: u16, query_name: &str) -> Option<String> {
+    let message = dns_packet::parse_message(packet)?;
+    if !message.is_response() || message.id != id {
+        return None;
+    }
+
+    message.records.into_iter().find_map(|record| match record.data {
+        RecordData::Ptr(target) if record.name.eq_ignore_ascii_case(query_name) => {
+            Some(target.trim_end_matches('.').to_string())
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 50);
+
+    /// Response to the query for IP with one PTR record, whose owner name is compressed to
+    /// a pointer to the question (offset 12)
+    fn response(id: u16, target: &[u8]) -> Vec<u8> {
+        let (_, _, mut packet) = reverse_query(IP);
+        packet[0..2].copy_from_slice(&id.to_be_bytes());
+        packet[2] = 0x80; // response
+        packet[7] = 1; // one answer
+        packet.extend_from_slice(&[0xC0, 0x0C]);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet.extend_from_slice(&30u32.to_be_bytes()); // TTL
+        packet.extend_from_slice(&(target.len() as u16).to_be_bytes());
+        packet.extend_from_slice(target);
+        packet
+    }
+
+    #[test]
+    fn test_reverse_query_encoding() {
+        let (id, name, packet) = reverse_query(IP);
+        assert_eq!(id, 0x0132 ^ 0x4c4c);
+        assert_eq!(name, "50.1.168.192.in-addr.arpa");
+        assert_eq!(&packet[..6], &[0x4d, 0x7e, 0, 0, 0, 1]);
+
+        let mut question = Vec::new();
+        for label in ["50", "1", "168", "192", "in-addr", "arpa"] {
+            question.push(label.len() as u8);
+            question.extend_from_slice(label.as_bytes());
+        }
+        question.push(0);
+        question.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        question.extend_from_slice(&CLASS_IN.to_be_bytes());
+        assert_eq!(&packet[12..], &question[..]);
+    }
+
+    #[test]
+    fn test_parse_compressed_answer() {
+        let (id, name, _) = reverse_query(IP);
+        let packet = response(id, b"\x0aDESKTOP-01\x00");
+        assert_eq!(parse_reverse_answer(&packet, id, &name).as_deref(), Some("DESKTOP-01"));
+
+        // Answers to other queries are ignored
+        assert!(parse_reverse_answer(&packet, id ^ 1, &name).is_none());
+        assert!(parse_reverse_answer(&packet, id, "51.1.168.192.in-addr.arpa").is_none());
+    }
+
+    #[test]
+    fn test_truncated_answer() {
+        let (id, name, _) = reverse_query(IP);
+        let packet = response(id, b"\x0aDESKTOP-01\x00");
+        for len in [0, 6, 20, packet.len() - 1] {
+            assert!(parse_reverse_answer(&packet[..len], id, &name).is_none());
+        }
+    }
+}
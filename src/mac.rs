@@ -0,0 +1,114 @@
+//! Parsed MAC addresses
+//! Accepts the common notations (colon, dash, Cisco dotted, bare hex) and always prints the
+//! canonical lowercase colon form used in devices.json
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MacAddress([u8; 6]);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseMacError(String);
+
+impl fmt::Display for ParseMacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid MAC address '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseMacError {}
+
+impl MacAddress {
+    pub fn new(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+
+    /// The 48 bits as a number, first octet most significant
+    pub fn to_u64(self) -> u64 {
+        self.0.iter().fold(0, |acc, &b| (acc << 8) | b as u64)
+    }
+}
+
+impl FromStr for MacAddress {
+    type Err = ParseMacError;
+
+    /// "aa:bb:cc:dd:ee:ff", "AA-BB-CC-DD-EE-FF", "aa bb cc dd ee ff", "aabb.ccdd.eeff" or "aabbccddeeff"
+    /// Separators must split the address into equal groups of 1-2, 4 or 12 digits
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseMacError(s.to_string());
+        let trimmed = s.trim();
+
+        let groups: Vec<&str> = trimmed.split([':', '-', '.', ' ']).collect();
+        let digits: String = match groups.len() {
+            1 if trimmed.len() == 12 => trimmed.to_string(),
+            3 if groups.iter().all(|g| g.len() == 4) => groups.concat(),
+            // Some tools drop leading zeros ("0:1b:21:a:b:c")
+            6 if groups.iter().all(|g| (1..=2).contains(&g.len())) => {
+                groups.iter().map(|g| format!("{:0>2}", g)).collect()
+            }
+            _ => return Err(error()),
+        };
+
+        let mut octets = [0u8; 6];
+        for (i, octet) in octets.iter_mut().enumerate() {
+            *octet = u8::from_str_radix(digits.get(i * 2..i * 2 + 2).ok_or_else(error)?, 16)
+                .map_err(|_| error())?;
+        }
+        Ok(Self(octets))
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        // Through pad() so table widths apply
+        f.pad(&format!("{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", a, b, c, d, e, g))
+    }
+}
+
+impl From<pnet::util::MacAddr> for MacAddress {
+    fn from(mac: pnet::util::MacAddr) -> Self {
+        Self(mac.octets())
+    }
+}
+
+impl Serialize for MacAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_formats() {
+        let expected = MacAddress::new([0xbc, 0x24, 0x11, 0x36, 0x2d, 0x6e]);
+        for text in ["bc:24:11:36:2d:6e", "BC-24-11-36-2D-6E", "bc24.1136.2d6e", "BC2411362D6E", "bc 24 11 36 2d 6e"] {
+            assert_eq!(text.parse::<MacAddress>(), Ok(expected));
+        }
+        assert_eq!(expected.to_string(), "bc:24:11:36:2d:6e");
+        assert_eq!("0:1b:21:a:b:c".parse::<MacAddress>().unwrap().to_string(), "00:1b:21:0a:0b:0c");
+
+        for text in ["", "192.168.1.10", "bc:24:11:36:2d", "bc:24:11:36:2d:6e:00", "zz:24:11:36:2d:6e"] {
+            assert!(text.parse::<MacAddress>().is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let mac: MacAddress = serde_json::from_str("\"BC:24:11:36:2D:6E\"").unwrap();
+        assert_eq!(serde_json::to_string(&mac).unwrap(), "\"bc:24:11:36:2d:6e\"");
+        assert!(serde_json::from_str::<MacAddress>("\"10.0.0.1\"").is_err());
+    }
+}
@@ -1,12 +1,38 @@
+mod classify;
+mod config;
+mod correlate;
+mod dhcp;
+mod dhcp_sniff;
+mod dns_packet;
+mod dns_sd;
+mod hostname_cache;
+mod lease_watch;
+mod llmnr;
+mod mac;
+mod mdns;
 mod models;
 mod monitor;
+mod netbios;
+mod oui;
+mod ports;
 mod proxmox;
+mod registry;
+mod reverse_dns;
 mod scanner;
+mod snmp;
+mod ssdp;
+mod state;
 mod utils;
 mod vendor;
 
 use clap::{Parser, Subcommand};
 use colored::*;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use models::HostnameSource;
+use reverse_dns::ReverseDns;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -30,6 +56,14 @@ enum Commands {
         /// Resolve hostnames (slower but shows device names)
         #[arg(long)]
         hostname: bool,
+
+        /// Print the devices as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+
+        /// Only show devices with this tag (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
     },
     /// Monitor the network for changes
     Monitor {
@@ -48,15 +82,103 @@ enum Commands {
         /// Resolve hostnames (slower but shows device names)
         #[arg(long)]
         hostname: bool,
+
+        /// Only show devices with this tag (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Set the alias, owner, tags, notes or location of a known device (an empty value clears it)
+    Edit {
+        /// Device ID, IP or MAC
+        device: String,
+
+        /// Name shown instead of the hostname
+        #[arg(long)]
+        alias: Option<String>,
+
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Add a tag (repeatable)
+        #[arg(long = "tag")]
+        add_tags: Vec<String>,
+
+        /// Remove a tag (repeatable)
+        #[arg(long = "untag")]
+        remove_tags: Vec<String>,
+
+        #[arg(long)]
+        notes: Option<String>,
+
+        #[arg(long)]
+        location: Option<String>,
+
+        /// Quarantine the device: reported whenever it comes online
+        #[arg(long, conflicts_with = "release")]
+        quarantine: bool,
+
+        /// Lift the quarantine
+        #[arg(long)]
+        release: bool,
+
+        /// Set the device class instead of inferring it
+        #[arg(long, value_enum, conflicts_with = "auto_class")]
+        class: Option<models::DeviceClass>,
+
+        /// Go back to the inferred device class
+        #[arg(long)]
+        auto_class: bool,
+    },
+    /// Resolve the hostname of a single IP, showing what each method answers
+    Lookup {
+        /// IP address to look up
+        ip: Ipv4Addr,
+
+        /// Method to try (repeatable). Tries every method in chain order if omitted
+        #[arg(short, long, value_enum)]
+        method: Vec<HostnameSource>,
+    },
+    /// Look up the vendor of MAC addresses (arguments or stdin), or manage the vendor database
+    #[command(args_conflicts_with_subcommands = true)]
+    Vendor {
+        #[command(subcommand)]
+        command: Option<VendorCommand>,
+
+        /// MAC addresses or OUIs in any common format. Read from stdin if omitted
+        macs: Vec<String>,
+
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum VendorCommand {
+    /// Import a downloaded registry file (IEEE oui.txt/oui.csv/mam.csv/oui36.csv or Wireshark manuf)
+    Update {
+        /// Registry file to import
+        #[arg(long)]
+        from: PathBuf,
     },
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    let config = config::load_config().unwrap_or_else(|e| {
+        eprintln!("Warning: ignoring invalid {}: {}", config::CONFIG_FILE, e);
+        config::Config::default()
+    });
 
     match cli.command {
-        Commands::Scan { range, interface, hostname } => {
+        Commands::Scan {
+            range,
+            interface,
+            hostname,
+            json,
+            tags,
+        } => {
             let target_ips = match utils::parse_ip_range(&range) {
                 Ok(ips) => ips,
                 Err(e) => {
@@ -64,23 +186,58 @@ async fn main() {
                     return;
                 }
             };
-            println!("GetMacRede v{}", env!("CARGO_PKG_VERSION"));
-            println!("Scanning {} IPs...", target_ips.len());
-            let devices = scanner::scan_network(interface, target_ips, hostname).await;
+            if !json {
+                println!("GetMacRede v{}", env!("CARGO_PKG_VERSION"));
+                println!("Scanning {} IPs...", target_ips.len());
+            }
+            let mut devices = scanner::scan_network(interface, target_ips, hostname, &config).await;
+
+            // Aliases and tags of the devices the monitor knows
+            correlate::apply_known(&mut devices, &monitor::load_devices().unwrap_or_default());
+            devices.retain(|device| device.matches_tags(&tags));
+            for device in &mut devices {
+                if device.user.quarantined && device.status == models::DeviceStatus::Online {
+                    device.status = models::DeviceStatus::Quarantined;
+                }
+            }
 
+            if json {
+                match serde_json::to_string_pretty(&devices) {
+                    Ok(output) => println!("{}", output),
+                    Err(e) => eprintln!("Error serializing devices: {}", e),
+                }
+                return;
+            }
+
+            // Names from the other resolvers get their own column in "all" mode
+            let show_others = config.resolver.mode == utils::ResolveMode::All;
             println!(
-                "{:<15} {:<17} {:<20} {:<10}",
-                "IP", "MAC", "HOSTNAME", "STATUS"
+                "{:<15} {:<17} {:<20} {:<8} {:<16}{}",
+                "IP",
+                "MAC",
+                "HOSTNAME",
+                "SOURCE",
+                "STATUS",
+                if show_others { " OTHER NAMES" } else { "" }
             );
-            println!("{}", "-".repeat(65));
+            println!("{}", "-".repeat(if show_others { 106 } else { 80 }));
             for device in devices {
-                let hostname = device.hostname.unwrap_or_else(|| "-".to_string());
+                let others = if show_others {
+                    format!(" {}", device.other_hostnames_label())
+                } else {
+                    String::new()
+                };
+                let (name, source) = device.name_and_source();
+                let source = source.unwrap_or_else(|| "-".to_string());
+                let hostname = name.unwrap_or("-");
                 println!(
-                    "{:<15} {:<17} {:<20} {:<10}",
+                    "{:<15} {:<17} {:<20} {:<8} {:<16}{}",
                     device.ip,
                     device.mac,
                     hostname.chars().take(20).collect::<String>(),
-                    device.status.to_string().green()
+                    source,
+                    device.status.colored(),
+                    others
                 );
             }
         }
@@ -89,8 +246,246 @@ async fn main() {
             interface,
             interval,
             hostname,
+            tags,
+        } => {
+            monitor::run_monitor(interface, range, interval, hostname, tags, &config).await;
+        }
+        Commands::Edit {
+            device,
+            alias,
+            owner,
+            add_tags,
+            remove_tags,
+            notes,
+            location,
+            quarantine,
+            release,
+            class,
+            auto_class,
+        } => {
+            let ip = device.parse::<Ipv4Addr>().ok();
+            let mac = device.parse::<mac::MacAddress>().ok();
+            // Read and written back under one lock, so a save of the monitor in between isn't lost
+            let updated = monitor::update_devices(|devices| {
+                let found = devices
+                    .iter_mut()
+                    .filter(|d| d.id == device || Some(d.ip) == ip || mac.is_some_and(|mac| d.has_mac(mac)))
+                    .max_by_key(|d| d.last_seen)?;
+
+                let user = &mut found.user;
+                if quarantine || release {
+                    user.quarantined = quarantine;
+                }
+                if class.is_some() || auto_class {
+                    user.class = class;
+                }
+                set_text(&mut user.alias, alias);
+                set_text(&mut user.owner, owner);
+                set_text(&mut user.notes, notes);
+                set_text(&mut user.location, location);
+                user.tags.retain(|tag| !remove_tags.iter().any(|removed| removed.eq_ignore_ascii_case(tag)));
+                for tag in add_tags {
+                    if !tag.is_empty() && !user.tags.iter().any(|own| own.eq_ignore_ascii_case(&tag)) {
+                        user.tags.push(tag);
+                    }
+                }
+
+                Some([
+                    ("ID", Some(found.id.clone())),
+                    ("IP", Some(found.ip.to_string())),
+                    ("MAC", Some(found.mac.to_string())),
+                    ("Alias", found.user.alias.clone()),
+                    ("Owner", found.user.owner.clone()),
+                    ("Tags", Some(found.user.tags.join(", ")).filter(|tags| !tags.is_empty())),
+                    ("Location", found.user.location.clone()),
+                    ("Notes", found.user.notes.clone()),
+                    ("Quarantined", found.user.quarantined.then(|| "yes".to_string())),
+                    (
+                        "Workgroup",
+                        found.netbios.as_ref().and_then(|status| status.workgroup.clone()),
+                    ),
+                    (
+                        "NetBIOS MAC",
+                        found.netbios.as_ref().and_then(|status| status.mac).map(|mac| {
+                            if found.netbios_mac_mismatch() {
+                                format!("{} (differs from ARP)", mac)
+                            } else {
+                                mac.to_string()
+                            }
+                        }),
+                    ),
+                    (
+                        "Class",
+                        found.class().map(|c| match found.user.class {
+                            Some(_) => format!("{} (manual)", c.class),
+                            None => format!("{} ({}%)", c.class, c.confidence),
+                        }),
+                    ),
+                ])
+            });
+            let summary = match updated {
+                Ok(Some(summary)) => summary,
+                Ok(None) => {
+                    eprintln!("No known device matches {}", device);
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("Error updating {}: {}", monitor::STORAGE_FILE, e);
+                    return;
+                }
+            };
+            for (label, value) in summary {
+                println!("{:<11} {}", label, value.as_deref().unwrap_or("-"));
+            }
+        }
+        Commands::Lookup { ip, method } => {
+            // Configured chain by default; per-source timeouts also apply to explicit methods
+            let steps: Vec<_> = if method.is_empty() {
+                config.resolver.chain.clone()
+            } else {
+                method
+                    .into_iter()
+                    .map(|source| utils::ResolverStep {
+                        source,
+                        timeout_ms: config
+                            .resolver
+                            .chain
+                            .iter()
+                            .find(|step| step.source == source)
+                            .and_then(|step| step.timeout_ms),
+                    })
+                    .collect()
+            };
+            let dhcp_leases = dhcp::load_leases(&config.dhcp);
+            let reverse_dns = ReverseDns::new(&config.dns);
+            // MAC of the device last seen at this IP, to check the one NetBIOS reports
+            let known_mac = monitor::load_devices()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|d| d.ip == ip)
+                .max_by_key(|d| d.last_seen)
+                .map(|d| d.mac);
+
+            println!("Looking up {}...", ip);
+            println!("{:<10} {:>8}  HOSTNAME", "METHOD", "TIME");
+            println!("{}", "-".repeat(60));
+            for step in steps {
+                let start = Instant::now();
+                let result = utils::lookup_hostname(
+                    step.source,
+                    IpAddr::V4(ip),
+                    Some(&dhcp_leases),
+                    &reverse_dns,
+                    None,
+                    step.timeout(),
+                )
+                .await;
+                let method_name = step.source.to_string();
+                let elapsed = format!("{}ms", start.elapsed().as_millis());
+                match result {
+                    Some(answer) => {
+                        let fcrdns = match answer.fcrdns {
+                            Some(true) => format!(" {}", "(FCrDNS ok)".green()),
+                            Some(false) => format!(" {}", "(FCrDNS failed)".red()),
+                            None => String::new(),
+                        };
+                        println!("{:<10} {:>8}  {}{}", method_name, elapsed, answer.hostname.green(), fcrdns);
+                        if let Some(status) = &answer.netbios {
+                            if let Some(workgroup) = &status.workgroup {
+                                println!("{:<10} {:>8}  Workgroup: {}", "", "", workgroup);
+                            }
+                            if let Some(mac) = status.mac {
+                                let mismatch = match known_mac {
+                                    Some(known) if !status.mac_matches(known) => {
+                                        format!(" {}", format!("(known device has {})", known).red())
+                                    }
+                                    _ => String::new(),
+                                };
+                                println!("{:<10} {:>8}  Reported MAC: {}{}", "", "", mac, mismatch);
+                            }
+                        }
+                    }
+                    None => println!("{:<10} {:>8}  {}", method_name, elapsed, "no answer".dimmed()),
+                }
+            }
+        }
+        Commands::Vendor {
+            command: None,
+            macs,
+            json,
         } => {
-            monitor::run_monitor(interface, range, interval, hostname).await;
+            let macs = if macs.is_empty() {
+                std::io::stdin()
+                    .lines()
+                    .map_while(Result::ok)
+                    // A line holding one space-separated MAC ("00 1b 21 8d 89 41") is kept whole
+                    .flat_map(|line| match line.trim().parse::<mac::MacAddress>() {
+                        Ok(_) => vec![line.trim().to_string()],
+                        Err(_) => line.split_whitespace().map(String::from).collect(),
+                    })
+                    .collect()
+            } else {
+                macs
+            };
+
+            let vendor_db = vendor::VendorDb::shared(&config.vendor);
+            let mut reports = Vec::new();
+            for mac in &macs {
+                match vendor_db.describe(mac) {
+                    Some(report) => reports.push(report),
+                    None => eprintln!("Warning: not a MAC address: {}", mac),
+                }
+            }
+
+            if json {
+                match serde_json::to_string_pretty(&reports) {
+                    Ok(output) => println!("{}", output),
+                    Err(e) => eprintln!("Error serializing vendors: {}", e),
+                }
+                return;
+            }
+
+            println!(
+                "{:<18} {:<32} {:<7} {:<21} {:<10} {:<6}",
+                "MAC", "VENDOR", "PREFIX", "CATEGORY", "CAST", "SCOPE"
+            );
+            println!("{}", "-".repeat(100));
+            for report in &reports {
+                let vendor = match report.vendor {
+                    Some(info) if info.custom => format!("{}*", info.name),
+                    Some(info) => info.name.to_string(),
+                    None => "-".to_string(),
+                };
+                println!(
+                    "{:<18} {:<32} {:<7} {:<21} {:<10} {}",
+                    report.mac,
+                    vendor,
+                    report.prefix_bits.map(|bits| format!("/{}", bits)).unwrap_or_default(),
+                    report.vendor.map(|info| info.category.to_string()).unwrap_or_default(),
+                    if report.multicast { "multicast" } else { "unicast" },
+                    if report.local { "local" } else { "global" }
+                );
+            }
         }
+        Commands::Vendor {
+            command: Some(VendorCommand::Update { from }),
+            ..
+        } => match oui::import(&from) {
+            Ok((imported, total)) => println!(
+                "Imported {} prefixes from {} into {} ({} total)",
+                imported,
+                from.display(),
+                oui::OUI_CACHE_FILE,
+                total
+            ),
+            Err(e) => eprintln!("Error importing {}: {}", from.display(), e),
+        },
+    }
+}
+
+/// Replace a user text field, an empty value clears it
+fn set_text(field: &mut Option<String>, value: Option<String>) {
+    if let Some(value) = value {
+        *field = Some(value).filter(|value| !value.is_empty());
     }
 }
@@ -0,0 +1,60 @@
+//! Multicast DNS (RFC 6762) reverse lookups
+//! Apple devices, Chromecasts and Linux hosts running avahi answer PTR queries
+//! for their own address on 224.0.0.251:5353 with their `.local` name
+
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout_at, Instant};
+
+use crate::dns_packet::{self, RecordData, CLASS_IN, CLASS_UNICAST_RESPONSE, TYPE_PTR};
+
+pub const MDNS_ADDR: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+pub const MDNS_PORT: u16 = 5353;
+
+/// How long to wait for answers after sending the query
+pub const DEFAULT_LISTEN_WINDOW: Duration = Duration::from_millis(800);
+
+/// Ask the local link who owns `ip` and return the announced name (e.g. "MacBook-Pro.local")
+/// The query is sent from an ephemeral port with the unicast-response bit set,
+/// so responders reply directly to us instead of to the multicast group
+pub async fn reverse_lookup(ip: Ipv4Addr, listen_window: Duration) -> Option<String> {
+    let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .ok()?;
+    // Link-local traffic: responders must discard packets whose TTL isn't 255
+    socket.set_multicast_ttl_v4(255).ok()?;
+
+    let query_name = dns_packet::reverse_name(ip);
+    let query = dns_packet::build_query(0, &query_name, TYPE_PTR, CLASS_IN | CLASS_UNICAST_RESPONSE);
+    socket
+        .send_to(&query, SocketAddrV4::new(MDNS_ADDR, MDNS_PORT))
+        .await
+        .ok()?;
+
+    let deadline = Instant::now() + listen_window;
+    let mut buf = [0u8; 9000];
+
+    loop {
+        let (len, _) = match timeout_at(deadline, socket.recv_from(&mut buf)).await {
+            Ok(Ok(received)) => received,
+            // Listen window closed or socket error
+            _ => return None,
+        };
+
+        let Some(message) = dns_packet::parse_message(&buf[..len]) else {
+            continue;
+        };
+        if !message.is_response() {
+            continue;
+        }
+
+        let name = message.records.into_iter().find_map(|record| match record.data {
+            RecordData::Ptr(target) if record.name.eq_ignore_ascii_case(&query_name) => Some(target),
+            _ => None,
+        });
+        if let Some(name) = name {
+            return Some(name.trim_end_matches('.').to_string());
+        }
+    }
+}
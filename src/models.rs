@@ -1,13 +1,54 @@
 use chrono::{DateTime, Utc};
+use colored::{ColoredString, Colorize};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::net::Ipv4Addr;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+use crate::dhcp_sniff::DhcpClientInfo;
+use crate::mac::MacAddress;
+use crate::netbios::NodeStatus;
+use crate::snmp::SnmpInfo;
+use crate::ssdp::UpnpInfo;
+use crate::vendor::{VendorCategory, VendorInfo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeviceStatus {
     Online,
     Offline,
+    /// MAC listed in blacklist.json
     Block,
+    /// Never answered a scan itself, only listed in a router's ARP table
+    Unknown,
+    /// Went online and offline too often within the flapping window
+    Flapping,
+    /// More than one MAC answers for its IP
+    Conflict,
+    /// Offline during one of its offline schedules
+    ExpectedOffline,
+    /// Marked by the user (`edit --quarantine`), reported whenever it comes online
+    Quarantined,
+}
+
+impl DeviceStatus {
+    /// Online and offline for flapping detection: every state but the offline ones is online
+    pub fn is_online(self) -> bool {
+        !matches!(self, DeviceStatus::Offline | DeviceStatus::ExpectedOffline)
+    }
+
+    /// Status label in its table color
+    pub fn colored(self) -> ColoredString {
+        let label = self.to_string();
+        match self {
+            DeviceStatus::Online => label.bright_green(),
+            DeviceStatus::Offline => label.yellow(),
+            DeviceStatus::Block => label.red().bold(),
+            DeviceStatus::Unknown => label.dimmed(),
+            DeviceStatus::Flapping => label.magenta(),
+            DeviceStatus::Conflict => label.bright_red(),
+            DeviceStatus::ExpectedOffline => label.cyan(),
+            DeviceStatus::Quarantined => label.bright_magenta().bold(),
+        }
+    }
 }
 
 impl fmt::Display for DeviceStatus {
@@ -16,82 +57,578 @@ impl fmt::Display for DeviceStatus {
             DeviceStatus::Online => write!(f, "Online"),
             DeviceStatus::Offline => write!(f, "Offline"),
             DeviceStatus::Block => write!(f, "Block"),
+            DeviceStatus::Unknown => write!(f, "Unknown"),
+            DeviceStatus::Flapping => write!(f, "Flapping"),
+            DeviceStatus::Conflict => write!(f, "Conflict"),
+            DeviceStatus::ExpectedOffline => write!(f, "Expected-offline"),
+            DeviceStatus::Quarantined => write!(f, "Quarantined"),
+        }
+    }
+}
+
+/// Method that produced a device hostname
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HostnameSource {
+    Dhcp,
+    Dns,
+    Mdns,
+    Llmnr,
+    Netbios,
+    /// sysName from the SNMP system group
+    Snmp,
+    /// Hostname option of a DHCP request seen by the monitor's passive listener
+    Sniffed,
+    /// Pinned by the user in hostnames.json
+    Manual,
+}
+
+impl fmt::Display for HostnameSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HostnameSource::Dhcp => write!(f, "dhcp"),
+            HostnameSource::Dns => write!(f, "dns"),
+            HostnameSource::Mdns => write!(f, "mdns"),
+            HostnameSource::Llmnr => write!(f, "llmnr"),
+            HostnameSource::Netbios => write!(f, "netbios"),
+            HostnameSource::Snmp => write!(f, "snmp"),
+            HostnameSource::Sniffed => write!(f, "sniffed"),
+            HostnameSource::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+/// What kind of device this is, inferred from its advertised services, SNMP details
+/// and DHCP fingerprint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceType {
+    Printer,
+    SmartTv,
+    MediaPlayer,
+    Speaker,
+    HomeKitAccessory,
+    Nas,
+    Computer,
+    Phone,
+    Router,
+    Switch,
+    AccessPoint,
+}
+
+impl fmt::Display for DeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceType::Printer => write!(f, "printer"),
+            DeviceType::SmartTv => write!(f, "smart TV"),
+            DeviceType::MediaPlayer => write!(f, "media player"),
+            DeviceType::Speaker => write!(f, "speaker"),
+            DeviceType::HomeKitAccessory => write!(f, "HomeKit accessory"),
+            DeviceType::Nas => write!(f, "NAS"),
+            DeviceType::Computer => write!(f, "computer"),
+            DeviceType::Phone => write!(f, "phone"),
+            DeviceType::Router => write!(f, "router"),
+            DeviceType::Switch => write!(f, "switch"),
+            DeviceType::AccessPoint => write!(f, "access point"),
         }
     }
 }
 
+/// Broad kind of device shown in the monitor, inferred from every signal a device gives
+/// (see `classify::infer_class`) or set by the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum DeviceClass {
+    Router,
+    SwitchAp,
+    Printer,
+    Phone,
+    Pc,
+    Server,
+    Vm,
+    Container,
+    Iot,
+    Media,
+    Nas,
+}
+
+impl DeviceClass {
+    /// Every class, in the order of the monitor header counts
+    pub const ALL: [DeviceClass; 11] = [
+        DeviceClass::Router,
+        DeviceClass::SwitchAp,
+        DeviceClass::Server,
+        DeviceClass::Pc,
+        DeviceClass::Phone,
+        DeviceClass::Printer,
+        DeviceClass::Nas,
+        DeviceClass::Media,
+        DeviceClass::Iot,
+        DeviceClass::Vm,
+        DeviceClass::Container,
+    ];
+
+    /// Short label for the monitor's CLASS column and header
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            DeviceClass::Router => "RTR",
+            DeviceClass::SwitchAp => "SW",
+            DeviceClass::Printer => "PRN",
+            DeviceClass::Phone => "PHN",
+            DeviceClass::Pc => "PC",
+            DeviceClass::Server => "SRV",
+            DeviceClass::Vm => "VM",
+            DeviceClass::Container => "CT",
+            DeviceClass::Iot => "IOT",
+            DeviceClass::Media => "TV",
+            DeviceClass::Nas => "NAS",
+        }
+    }
+}
+
+impl From<DeviceType> for DeviceClass {
+    fn from(device_type: DeviceType) -> Self {
+        match device_type {
+            DeviceType::Printer => DeviceClass::Printer,
+            DeviceType::SmartTv | DeviceType::MediaPlayer | DeviceType::Speaker => DeviceClass::Media,
+            DeviceType::HomeKitAccessory => DeviceClass::Iot,
+            DeviceType::Nas => DeviceClass::Nas,
+            DeviceType::Computer => DeviceClass::Pc,
+            DeviceType::Phone => DeviceClass::Phone,
+            DeviceType::Router => DeviceClass::Router,
+            DeviceType::Switch | DeviceType::AccessPoint => DeviceClass::SwitchAp,
+        }
+    }
+}
+
+impl fmt::Display for DeviceClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceClass::Router => write!(f, "router/gateway"),
+            DeviceClass::SwitchAp => write!(f, "switch/AP"),
+            DeviceClass::Printer => write!(f, "printer"),
+            DeviceClass::Phone => write!(f, "phone"),
+            DeviceClass::Pc => write!(f, "PC"),
+            DeviceClass::Server => write!(f, "server"),
+            DeviceClass::Vm => write!(f, "VM"),
+            DeviceClass::Container => write!(f, "container"),
+            DeviceClass::Iot => write!(f, "IoT"),
+            DeviceClass::Media => write!(f, "TV/media"),
+            DeviceClass::Nas => write!(f, "NAS"),
+        }
+    }
+}
+
+/// A device class and how sure the guess is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Classification {
+    pub class: DeviceClass,
+    /// 0-100, 100 when set by the user
+    pub confidence: u8,
+}
+
+/// A resolved hostname and the method that found it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostnameAnswer {
+    pub hostname: String,
+    pub source: HostnameSource,
+    /// Forward-confirmed reverse DNS result, only set for DNS answers with checking enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fcrdns: Option<bool>,
+    /// Workgroup and the MAC the host reports, only set for NetBIOS answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub netbios: Option<NodeStatus>,
+}
+
+impl HostnameAnswer {
+    pub fn new(hostname: String, source: HostnameSource) -> Self {
+        Self {
+            hostname,
+            source,
+            fcrdns: None,
+            netbios: None,
+        }
+    }
+}
+
+/// Earlier MACs remembered per device
+const MAX_MAC_ALIASES: usize = 16;
+
+/// Past IP/MAC bindings remembered per device
+const MAX_HISTORY: usize = 32;
+
+/// Details entered by the user (`edit` command or devices.json), never changed by scans
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserInfo {
+    /// Shown instead of the hostname
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Set with `edit --quarantine`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub quarantined: bool,
+    /// Set with `edit --class`, replaces the inferred class
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<DeviceClass>,
+}
+
+/// A change between online and offline, for flapping detection
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    pub at: DateTime<Utc>,
+    pub online: bool,
+}
+
+/// An IP/MAC pair a device used, and when
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    pub ip: Ipv4Addr,
+    pub mac: MacAddress,
+    pub from: DateTime<Utc>,
+    /// When the device was first seen with another IP or MAC
+    pub to: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Device {
-    pub mac: String,
-    pub ip: String,
+    /// Stable identifier, kept when the device changes IP or MAC
+    /// Missing in devices saved by earlier versions, made from the MAC on load
+    #[serde(default)]
+    pub id: String,
+    pub mac: MacAddress,
+    pub ip: Ipv4Addr,
     pub hostname: Option<String>,
+    /// Resolver that produced the hostname
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname_source: Option<HostnameSource>,
+    /// Forward-confirmed reverse DNS result for DNS hostnames (None if not checked)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fcrdns: Option<bool>,
+    /// Names from the other resolvers when the chain runs in "all" mode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other_hostnames: Vec<HostnameAnswer>,
+    /// Workgroup/domain and MAC from the NetBIOS node status, when NetBIOS answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub netbios: Option<NodeStatus>,
+    /// Friendly name and model from the device's UPnP description (SSDP)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upnp: Option<UpnpInfo>,
+    /// DNS-SD service types advertised over mDNS (e.g. "_ipp._tcp")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<String>,
+    /// SNMP system group, for managed switches, APs and printers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snmp: Option<SnmpInfo>,
+    /// Hostname, vendor class and fingerprint from the device's own DHCP requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dhcp_client: Option<DhcpClientInfo>,
+    /// TCP ports that accepted a connection (only probed when enabled)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub open_ports: Vec<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_type: Option<DeviceType>,
+    /// Inferred class, see `class` for the one to show
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classification: Option<Classification>,
     pub vendor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor_category: Option<VendorCategory>,
+    /// Vendor taken from custom_vendors.json
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub vendor_custom: bool,
     pub last_seen: DateTime<Utc>,
+    /// Missing in devices saved by earlier versions, filled in from `last_seen` on load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<DateTime<Utc>>,
+    /// Scans that found the device
+    #[serde(default)]
+    pub sightings: u64,
+    /// Earlier IP/MAC bindings, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<Binding>,
     pub status: DeviceStatus,
+    /// Recent online/offline changes, oldest first (the latest is always kept)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<Transition>,
     /// Virtual MAC address (e.g., from Proxmox bridge/veth) if different from real MAC
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub virtual_mac: Option<String>,
+    pub virtual_mac: Option<MacAddress>,
+    /// Earlier randomized MACs of this device, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mac_aliases: Vec<MacAddress>,
+    #[serde(flatten)]
+    pub user: UserInfo,
 }
 
 impl Device {
-    pub fn new(mac: String, ip: String, hostname: Option<String>, vendor: Option<String>) -> Self {
-        // Validate that IP is actually an IP address, not a MAC
-        if !Self::is_valid_ip(&ip) {
-            eprintln!("WARNING: Invalid IP detected: '{}' (MAC was: '{}')", ip, mac);
-            eprintln!("This device will be skipped to prevent data corruption.");
-        }
-
-        // Validate that MAC is actually a MAC address, not an IP
-        if !Self::is_valid_mac(&mac) {
-            eprintln!("WARNING: Invalid MAC detected: '{}' (IP was: '{}')", mac, ip);
-        }
-
+    pub fn new(mac: MacAddress, ip: Ipv4Addr, hostname: Option<String>, vendor: Option<&VendorInfo>) -> Self {
+        let now = Utc::now();
         Self {
+            id: Self::id_from_mac(mac),
             mac,
             ip,
             hostname,
-            vendor,
-            last_seen: Utc::now(),
+            hostname_source: None,
+            fcrdns: None,
+            other_hostnames: Vec::new(),
+            netbios: None,
+            upnp: None,
+            services: Vec::new(),
+            snmp: None,
+            dhcp_client: None,
+            open_ports: Vec::new(),
+            device_type: None,
+            classification: None,
+            vendor_category: vendor.map(|info| info.category),
+            vendor_custom: vendor.is_some_and(|info| info.custom),
+            vendor: vendor.map(|info| info.name.to_string()),
+            last_seen: now,
+            first_seen: Some(now),
+            sightings: 1,
+            history: Vec::new(),
             status: DeviceStatus::Online,
+            transitions: Vec::new(),
             virtual_mac: None,
+            mac_aliases: Vec::new(),
+            user: UserInfo::default(),
         }
     }
 
-    /// Validates if a string is a valid IPv4 address
-    pub fn is_valid_ip(ip: &str) -> bool {
-        ip.parse::<Ipv4Addr>().is_ok()
+    /// Identifier for a device first seen with `mac`
+    pub fn id_from_mac(mac: MacAddress) -> String {
+        format!("{:012x}", mac.to_u64())
     }
 
-    /// Validates if a string is a valid MAC address format
-    pub fn is_valid_mac(mac: &str) -> bool {
-        if mac.is_empty() {
-            return false;
+    /// Vendor of the current MAC
+    pub fn set_vendor(&mut self, vendor: Option<&VendorInfo>) {
+        self.vendor_category = vendor.map(|info| info.category);
+        self.vendor_custom = vendor.is_some_and(|info| info.custom);
+        self.vendor = vendor.map(|info| info.name.to_string());
+    }
+
+    /// VM, container or virtual interface: a virtual MAC was seen or the vendor is virtual
+    pub fn is_virtual(&self) -> bool {
+        self.virtual_mac.is_some() || self.vendor_category.is_some_and(VendorCategory::is_virtual)
+    }
+
+    /// Class set by the user, or the inferred one
+    pub fn class(&self) -> Option<Classification> {
+        self.user
+            .class
+            .map(|class| Classification { class, confidence: 100 })
+            .or(self.classification)
+    }
+
+    /// Switch to a new MAC, keeping the current one as an alias
+    pub fn replace_mac(&mut self, mac: MacAddress) {
+        let previous = std::mem::replace(&mut self.mac, mac);
+        self.mac_aliases.retain(|alias| *alias != previous && *alias != self.mac);
+        self.mac_aliases.push(previous);
+        if self.mac_aliases.len() > MAX_MAC_ALIASES {
+            self.mac_aliases.remove(0);
         }
+    }
 
-        // MAC should be in format XX:XX:XX:XX:XX:XX
-        let parts: Vec<&str> = mac.split(':').collect();
-        if parts.len() != 6 {
+    /// Start of the current IP/MAC binding
+    pub fn bound_since(&self) -> DateTime<Utc> {
+        self.history
+            .last()
+            .map(|binding| binding.to)
+            .or(self.first_seen)
+            .unwrap_or(self.last_seen)
+    }
+
+    /// Keep `previous` (IP, MAC) in the history if the device no longer uses it
+    pub fn record_binding(&mut self, previous: (Ipv4Addr, MacAddress), now: DateTime<Utc>) -> bool {
+        let (ip, mac) = previous;
+        if (self.ip, self.mac) == previous {
             return false;
         }
+        let from = self.bound_since();
+        self.history.push(Binding { ip, mac, from, to: now });
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        true
+    }
 
-        // Each part should be exactly 2 hex characters
-        parts.iter().all(|part| {
-            part.len() == 2 && part.chars().all(|c| c.is_ascii_hexdigit())
-        })
+    /// Uses `mac` now or used it before
+    pub fn has_mac(&self, mac: MacAddress) -> bool {
+        self.mac == mac || self.virtual_mac == Some(mac) || self.mac_aliases.contains(&mac)
+    }
+
+    /// Use the first answer as the hostname and keep the rest as alternatives
+    /// An empty list leaves the current hostname untouched
+    pub fn set_hostnames(&mut self, answers: &[HostnameAnswer]) {
+        let Some((primary, others)) = answers.split_first() else {
+            return;
+        };
+        self.hostname = Some(primary.hostname.clone());
+        self.hostname_source = Some(primary.source);
+        self.fcrdns = primary.fcrdns;
+        self.other_hostnames = others.to_vec();
+        self.netbios = answers.iter().find_map(|a| a.netbios.clone());
     }
 
-    /// Validates the device data integrity
-    pub fn validate(&self) -> bool {
-        let ip_valid = Self::is_valid_ip(&self.ip);
-        let mac_valid = Self::is_valid_mac(&self.mac);
+    /// True when the MAC a host reports over NetBIOS isn't the one seen via ARP,
+    /// usually because of a MAC-rewriting bridge or repeater in between
+    pub fn netbios_mac_mismatch(&self) -> bool {
+        self.netbios.as_ref().is_some_and(|status| !status.mac_matches(self.mac))
+    }
+
+    /// Other names as "name (source)", comma separated
+    pub fn other_hostnames_label(&self) -> String {
+        self.other_hostnames
+            .iter()
+            .map(|a| format!("{} ({})", a.hostname, a.source))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 
-        if !ip_valid {
-            eprintln!("VALIDATION ERROR: Device has invalid IP: '{}' (MAC: '{}')", self.ip, self.mac);
+    /// Name for notifications: the alias, the hostname, or the MAC
+    pub fn display_name(&self) -> String {
+        self.user
+            .alias
+            .clone()
+            .or_else(|| self.hostname.clone())
+            .unwrap_or_else(|| self.mac.to_string())
+    }
+
+    /// Name for the tables: the alias, or the hostname with its source
+    pub fn name_and_source(&self) -> (Option<&str>, Option<String>) {
+        match &self.user.alias {
+            Some(alias) => (Some(alias), Some("alias".to_string())),
+            None => (self.hostname.as_deref(), self.source_label()),
         }
-        if !mac_valid {
-            eprintln!("VALIDATION ERROR: Device has invalid MAC: '{}' (IP: '{}')", self.mac, self.ip);
+    }
+
+    /// Has one of `tags` (case-insensitive), or no tag filter is given
+    pub fn matches_tags(&self, tags: &[String]) -> bool {
+        tags.is_empty()
+            || tags
+                .iter()
+                .any(|tag| self.user.tags.iter().any(|own| own.eq_ignore_ascii_case(tag)))
+    }
+
+    /// Hostname source for display, with the FCrDNS result appended ("dns✓" / "dns✗")
+    pub fn source_label(&self) -> Option<String> {
+        let source = self.hostname_source?;
+        Some(match self.fcrdns {
+            Some(true) => format!("{}✓", source),
+            Some(false) => format!("{}✗", source),
+            None => source.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_old_devices_json() {
+        // Written by earlier versions: mapped MACs were stored uppercase
+        let json = r#"{
+            "mac": "BC:24:11:36:2D:6E",
+            "ip": "192.168.15.31",
+            "hostname": null,
+            "vendor": "Proxmox Server Solutions GmbH",
+            "last_seen": "2025-01-10T12:00:00Z",
+            "status": "Online",
+            "virtual_mac": "bc:24:11:00:00:01"
+        }"#;
+        let device: Device = serde_json::from_str(json).unwrap();
+        assert_eq!(device.mac.to_string(), "bc:24:11:36:2d:6e");
+        assert_eq!(device.ip, Ipv4Addr::new(192, 168, 15, 31));
+        assert!(device.has_mac("bc:24:11:00:00:01".parse().unwrap()));
+
+        let saved = serde_json::to_value(&device).unwrap();
+        assert_eq!(saved["mac"], "bc:24:11:36:2d:6e");
+        assert_eq!(saved["ip"], "192.168.15.31");
+
+        // IP and MAC swapped, as some early scans saved them
+        let swapped = json
+            .replace("\"BC:24:11:36:2D:6E\"", "\"swap\"")
+            .replace("\"192.168.15.31\"", "\"BC:24:11:36:2D:6E\"")
+            .replace("\"swap\"", "\"192.168.15.31\"");
+        assert!(serde_json::from_str::<Device>(&swapped).is_err());
+    }
+
+    #[test]
+    fn test_user_info() {
+        let mut device = Device::new("bc:24:11:36:2d:6e".parse().unwrap(), Ipv4Addr::new(192, 168, 15, 144), Some("pixel".to_string()), None);
+        assert_eq!(device.name_and_source().0, Some("pixel"));
+        assert!(device.matches_tags(&[]));
+        assert!(!device.matches_tags(&["family".to_string()]));
+
+        device.user.alias = Some("Ana's phone".to_string());
+        device.user.tags = vec!["Family".to_string()];
+        assert_eq!(device.name_and_source(), (Some("Ana's phone"), Some("alias".to_string())));
+        assert!(device.matches_tags(&["work".to_string(), "family".to_string()]));
+
+        // Saved next to the scanned fields
+        let saved = serde_json::to_value(&device).unwrap();
+        assert_eq!(saved["alias"], "Ana's phone");
+        assert!(saved.get("owner").is_none());
+        assert_eq!(serde_json::from_value::<Device>(saved).unwrap().user, device.user);
+    }
+
+    #[test]
+    fn test_record_binding() {
+        let laptop: MacAddress = "3c:22:fb:00:00:01".parse().unwrap();
+        let mut device = Device::new(laptop, Ipv4Addr::new(192, 168, 1, 20), None, None);
+        let start = device.bound_since();
+
+        // Still the same binding
+        assert!(!device.record_binding((device.ip, device.mac), Utc::now()));
+
+        let moved = Utc::now();
+        device.ip = Ipv4Addr::new(192, 168, 1, 35);
+        assert!(device.record_binding((Ipv4Addr::new(192, 168, 1, 20), laptop), moved));
+        assert_eq!(
+            device.history,
+            vec![Binding { ip: Ipv4Addr::new(192, 168, 1, 20), mac: laptop, from: start, to: moved }]
+        );
+        assert_eq!(device.bound_since(), moved);
+
+        for _ in 0..MAX_HISTORY + 5 {
+            let previous = (device.ip, device.mac);
+            device.ip = Ipv4Addr::from(u32::from(device.ip) + 1);
+            device.record_binding(previous, Utc::now());
         }
+        assert_eq!(device.history.len(), MAX_HISTORY);
+    }
+
+    #[test]
+    fn test_set_hostnames_keeps_netbios_details() {
+        let mut device = Device::new(
+            "68:5b:35:8d:89:41".parse().unwrap(),
+            "192.168.1.50".parse().unwrap(),
+            None,
+            None,
+        );
+        let dns = HostnameAnswer::new("desktop.lan".to_string(), HostnameSource::Dns);
+        let netbios = HostnameAnswer {
+            netbios: Some(NodeStatus {
+                name: Some("DESKTOP".to_string()),
+                workgroup: Some("WORKGROUP".to_string()),
+                mac: "00:11:22:33:44:55".parse().ok(),
+            }),
+            ..HostnameAnswer::new("DESKTOP".to_string(), HostnameSource::Netbios)
+        };
+        device.set_hostnames(&[dns, netbios]);
 
-        ip_valid && mac_valid
+        assert_eq!(device.hostname.as_deref(), Some("desktop.lan"));
+        let status = device.netbios.as_ref().unwrap();
+        assert_eq!(status.workgroup.as_deref(), Some("WORKGROUP"));
+        assert!(device.netbios_mac_mismatch());
     }
 }
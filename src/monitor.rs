@@ -1,19 +1,29 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use colored::*;
 use fs2::FileExt;
 use notify_rust::Notification;
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, BufReader, BufWriter};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::time;
 
-use crate::models::{Device, DeviceStatus};
+use crate::classify;
+use crate::config::Config;
+use crate::correlate;
+use crate::dhcp;
+use crate::dhcp_sniff::{self, DhcpClientInfo};
+use crate::lease_watch;
+use crate::mac::MacAddress;
+use crate::models::{Device, DeviceClass, DeviceStatus, UserInfo};
 use crate::proxmox;
 use crate::scanner;
+use crate::ssdp;
+use crate::state::{self, Sighting};
 use crate::utils;
-use crate::vendor::VendorDb;
+use crate::vendor::{VendorCategory, VendorDb};
 
 /// Helper function to pad a colored string to a specific width
 /// ANSI color codes don't count toward visible width, so we need custom padding
@@ -24,19 +34,28 @@ fn pad_colored(s: String, width: usize) -> String {
     }
 
     // Count visible characters (excluding ANSI codes)
-    let visible_len = console::strip_ansi_codes(&s).len();
-    let padding = if visible_len < width {
-        width - visible_len
-    } else {
-        0
-    };
+    let visible_len = console::strip_ansi_codes(&s).chars().count();
+    let padding = width.saturating_sub(visible_len);
     format!("{}{}", s, " ".repeat(padding))
 }
 
-const STORAGE_FILE: &str = "devices.json";
+pub const STORAGE_FILE: &str = "devices.json";
 const BLACKLIST_FILE: &str = "blacklist.json";
 
-pub async fn run_monitor(interface: Option<String>, range: String, interval: u64, resolve_hostnames: bool) {
+/// Delay before re-reading lease files after a change
+const LEASE_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Sniffed DHCP clients kept until a scan finds their device
+const MAX_PENDING_CLIENTS: usize = 1024;
+
+pub async fn run_monitor(
+    interface: Option<String>,
+    range: String,
+    interval: u64,
+    resolve_hostnames: bool,
+    tags: Vec<String>,
+    config: &Config,
+) {
     let target_ips = match utils::parse_ip_range(&range) {
         Ok(ips) => ips,
         Err(e) => {
@@ -68,7 +87,7 @@ pub async fn run_monitor(interface: Option<String>, range: String, interval: u64
     let mac_mappings = proxmox::load_mac_mappings().unwrap_or_default();
 
     // Create vendor database for lookup
-    let vendor_db = VendorDb::new();
+    let vendor_db = VendorDb::shared(&config.vendor);
 
     // Apply MAC mappings and vendor lookup to existing devices
     let mut mapping_applied = false;
@@ -77,55 +96,152 @@ pub async fn run_monitor(interface: Option<String>, range: String, interval: u64
     for mut d in known_devices {
         // Apply MAC mapping if exists
         if let Some(real_mac) = mac_mappings.get(&d.ip) {
-            if &d.mac != real_mac {
+            if d.mac != *real_mac {
                 if d.virtual_mac.is_none() {
-                    d.virtual_mac = Some(d.mac.clone());
+                    d.virtual_mac = Some(d.mac);
                 }
-                d.mac = real_mac.clone();
+                d.mac = *real_mac;
                 mapping_applied = true;
             }
-        } else if d.virtual_mac.is_none() && vendor_db.is_virtual(&d.mac) {
+        } else if d.virtual_mac.is_none() && vendor_db.is_virtual(d.mac) {
             // Auto-detect virtual MAC for existing devices without manual mapping
-            d.virtual_mac = Some(d.mac.clone());
+            d.virtual_mac = Some(d.mac);
             mapping_applied = true;
-        }
-
-        // Apply vendor lookup if not present
-        if d.vendor.is_none() {
-            d.vendor = vendor_db.lookup(&d.mac);
+        } else if d.virtual_mac == Some(d.mac) && vendor_db.is_randomized(d.mac) {
+            // Earlier versions took private client MACs for virtual ones
+            d.virtual_mac = None;
             mapping_applied = true;
         }
 
-        // Validate device before inserting into map
-        if !d.validate() {
-            eprintln!("WARNING: Skipping invalid device during initialization: IP='{}', MAC='{}'", d.ip, d.mac);
-            continue;
+        // Refresh the vendor: missing, saved before categories existed, or changed in
+        // custom_vendors.json since the last run
+        if let Some(info) = vendor_db.lookup(d.mac)
+            && (d.vendor.as_deref() != Some(&*info.name)
+                || d.vendor_category != Some(info.category)
+                || d.vendor_custom != info.custom)
+        {
+            d.set_vendor(Some(info));
+            mapping_applied = true;
         }
 
-        // Use IP as key instead of MAC to prevent duplicates
-        // If there's already a device with this IP, keep the most recently seen one
-        if let Some(existing) = device_map.get(&d.ip) {
+        // Earlier versions kept one entry per IP, so a device that changed IP can be listed
+        // more than once: keep the most recently seen one
+        if let Some(existing) = device_map.get(&d.id) {
             if d.last_seen > existing.last_seen {
-                device_map.insert(d.ip.clone(), d);
-                mapping_applied = true;
+                if d.user == UserInfo::default() {
+                    d.user = existing.user.clone();
+                }
+                device_map.insert(d.id.clone(), d);
             }
+            mapping_applied = true;
         } else {
-            device_map.insert(d.ip.clone(), d);
+            device_map.insert(d.id.clone(), d);
         }
     }
 
     // Save devices if mappings were applied
-    if mapping_applied {
-        let devices: Vec<Device> = device_map.values().cloned().collect();
-        if let Err(e) = save_devices(&devices) {
-            eprintln!("Failed to save devices after applying MAC mappings: {}", e);
-        }
+    if mapping_applied
+        && let Err(e) = save_map(&mut device_map)
+    {
+        eprintln!("Failed to save devices after applying MAC mappings: {}", e);
     }
 
     let mut interval_timer = time::interval(Duration::from_secs(interval));
 
+    // Watch lease files so new hostnames show up between scans
+    let mut lease_changes = lease_watch::spawn(&config.dhcp.lease_files());
+    // SSDP announcements fill in friendly names as devices come alive
+    let mut upnp_announcements = if config.ssdp.enabled {
+        ssdp::listen_notify()
+    } else {
+        None
+    };
+    // DHCP requests name devices and fingerprint their OS as they join
+    let mut dhcp_clients = if config.dhcp.sniff {
+        dhcp_sniff::spawn(interface.as_deref())
+    } else {
+        None
+    };
+    // Clients seen before their device showed up in a scan, by MAC
+    let mut pending_clients: HashMap<MacAddress, DhcpClientInfo> = HashMap::new();
+    let mut last_scan = Utc::now();
+    let mut conflicts = state::ConflictTracker::default();
+
     loop {
-        interval_timer.tick().await;
+        tokio::select! {
+            _ = interval_timer.tick() => {}
+            client = next_event(&mut dhcp_clients) => {
+                let Some((mac, info)) = client else {
+                    dhcp_clients = None;
+                    continue;
+                };
+                let updated = match find_by_mac(&mut device_map, mac) {
+                    Some(device) => dhcp_sniff::apply_client(device, info),
+                    None => {
+                        if pending_clients.len() < MAX_PENDING_CLIENTS {
+                            pending_clients.insert(mac, info);
+                        }
+                        false
+                    }
+                };
+                if updated {
+                    if let Err(e) = save_map(&mut device_map) {
+                        eprintln!("Failed to save devices: {}", e);
+                    }
+                    print_table(&device_map, &range, &tags, last_scan);
+                }
+                continue;
+            }
+            announcement = next_event(&mut upnp_announcements) => {
+                let Some((ip, upnp)) = announcement else {
+                    upnp_announcements = None;
+                    continue;
+                };
+                // Unknown hosts are picked up (with their SSDP answer) by the next scan
+                if let Some(device) = device_map.values_mut().find(|d| d.ip == ip)
+                    && device.upnp.as_ref() != Some(&upnp)
+                {
+                    device.upnp = Some(upnp);
+                    device.classification = classify::infer_class(device);
+                    if let Err(e) = save_map(&mut device_map) {
+                        eprintln!("Failed to save devices: {}", e);
+                    }
+                    print_table(&device_map, &range, &tags, last_scan);
+                }
+                continue;
+            }
+            changed = next_event(&mut lease_changes) => {
+                if changed.is_none() {
+                    // Watcher thread stopped, keep running on scans alone
+                    lease_changes = None;
+                    continue;
+                }
+
+                // Let the DHCP server finish writing, then coalesce the burst of events
+                time::sleep(LEASE_DEBOUNCE).await;
+                if let Some(rx) = lease_changes.as_mut() {
+                    while rx.try_recv().is_ok() {}
+                }
+
+                let leases = dhcp::load_leases(&config.dhcp);
+                let mut lease_updates = false;
+                for device in device_map.values_mut() {
+                    lease_updates |= dhcp::apply_lease(device, &leases, vendor_db, true);
+                }
+                if lease_updates {
+                    if let Err(e) = save_map(&mut device_map) {
+                        eprintln!("Failed to save devices: {}", e);
+                    }
+                    print_table(&device_map, &range, &tags, last_scan);
+                }
+                continue;
+            }
+        }
+
+        // Pick up aliases and tags edited since the last scan
+        if let Ok(mut saved) = load_devices() {
+            sync_user_info(&mut device_map, &mut saved);
+        }
 
         // Flush ARP cache before scanning for fresh MAC addresses
         if let Err(e) = utils::flush_arp_cache(interface.as_deref()) {
@@ -136,20 +252,20 @@ pub async fn run_monitor(interface: Option<String>, range: String, interval: u64
         // Load Blacklist
         let blacklist = load_blacklist().unwrap_or_default();
 
-        let mut found_devices = scanner::scan_network(interface.clone(), target_ips.clone(), resolve_hostnames).await;
+        let mut found_devices = scanner::scan_network(interface.clone(), target_ips.clone(), resolve_hostnames, config).await;
 
         // Correct MAC addresses using manual mappings and auto-detect virtual MACs
         for device in &mut found_devices {
             if let Some(real_mac) = mac_mappings.get(&device.ip) {
                 // Store the virtual MAC and replace with real MAC
-                device.virtual_mac = Some(device.mac.clone());
-                device.mac = real_mac.clone();
+                device.virtual_mac = Some(device.mac);
+                device.mac = *real_mac;
                 // Update vendor for the real MAC
-                device.vendor = vendor_db.lookup(&device.mac);
-            } else if vendor_db.is_virtual(&device.mac) {
+                device.set_vendor(vendor_db.lookup(device.mac));
+            } else if vendor_db.is_virtual(device.mac) {
                 // Auto-detect virtual MAC without manual mapping
                 // Move the virtual MAC to virtual_mac field and use a placeholder for mac
-                device.virtual_mac = Some(device.mac.clone());
+                device.virtual_mac = Some(device.mac);
                 // Keep the virtual MAC in the mac field but mark it clearly in vendor
                 // This ensures the device has a valid MAC identifier
             }
@@ -157,70 +273,147 @@ pub async fn run_monitor(interface: Option<String>, range: String, interval: u64
 
         let mut changes = false;
 
-        // 1. Process Found Devices
-        for found in found_devices {
-            // Validate found device before processing
-            if !found.validate() {
-                eprintln!("WARNING: Scanner returned invalid device, skipping: IP='{}', MAC='{}'", found.ip, found.mac);
-                continue;
+        // DHCP requests sniffed before the scan identify new randomized MACs
+        for device in &mut found_devices {
+            if let Some(info) = pending_clients.remove(&device.mac) {
+                dhcp_sniff::apply_client(device, info);
             }
+        }
+        let found_macs: HashSet<MacAddress> = found_devices.iter().map(|d| d.mac).collect();
+
+        // The same device keeps its entry when DHCP gives it a new IP or it rotates its MAC
+        let known_ids: Vec<Option<String>> = found_devices
+            .iter()
+            .map(|found| correlate::find_device(device_map.values(), found, &found_macs).map(|d| d.id.clone()))
+            .collect();
+        let conflict_ips = conflicts.update(
+            found_devices
+                .iter()
+                .zip(&known_ids)
+                .map(|(found, id)| (found.ip, found.mac, id.as_deref())),
+        );
 
+        // 1. Process Found Devices
+        let mut seen_ids = HashSet::new();
+        for (found, known_id) in found_devices.into_iter().zip(known_ids) {
             let is_blocked = blacklist.contains(&found.mac);
 
-            if let Some(existing) = device_map.get_mut(&found.ip) {
-                // Update existing device
-                if existing.status == DeviceStatus::Offline {
-                    notify("Device Online", &format!("{} is back online", found.ip));
-                    existing.status = if is_blocked {
-                        DeviceStatus::Block
-                    } else {
-                        DeviceStatus::Online
-                    };
-                    changes = true;
-                } else if is_blocked && existing.status != DeviceStatus::Block {
-                    existing.status = DeviceStatus::Block;
-                    changes = true;
-                } else if !is_blocked && existing.status == DeviceStatus::Block {
-                    existing.status = DeviceStatus::Online;
+            if let Some(existing) = known_id.and_then(|id| device_map.get_mut(&id)) {
+                seen_ids.insert(existing.id.clone());
+                let previous_binding = (existing.ip, existing.mac);
+                if existing.ip != found.ip {
+                    notify(
+                        "IP Changed",
+                        &format!("{} moved from {} to {}", existing.display_name(), existing.ip, found.ip),
+                    );
+                    existing.ip = found.ip;
                     changes = true;
                 }
 
+                // Update existing device
+                let previous_status = existing.status;
+                let sighting = Sighting::Seen {
+                    confirmed: found.status != DeviceStatus::Unknown,
+                    conflict: conflict_ips.contains(&found.ip),
+                };
+                if let Some((summary, body)) =
+                    state::transition(existing, sighting, is_blocked, Utc::now(), &config.states)
+                {
+                    notify(summary, &body);
+                }
+                changes |= existing.status != previous_status;
+
                 // Update MAC if changed (for devices with dynamic MACs)
                 if existing.mac != found.mac {
                     // If the new MAC is virtual, move it to virtual_mac
                     if found.virtual_mac.is_some() {
-                        existing.virtual_mac = found.virtual_mac.clone();
+                        existing.virtual_mac = found.virtual_mac;
                         // Don't update the mac field if it's already a real MAC
-                        if existing.virtual_mac.as_ref() != Some(&existing.mac) {
+                        if existing.virtual_mac != Some(existing.mac) {
                             // Keep the real MAC if we have one
                         } else {
                             // Update if both are virtual
-                            existing.mac = found.mac.clone();
+                            existing.mac = found.mac;
                         }
                     } else {
-                        // New MAC is not virtual, update it
-                        existing.mac = found.mac.clone();
+                        // Same device with a rotated private MAC, or a new one matched by its
+                        // DHCP client identifier: keep the old MAC as an alias
+                        existing.replace_mac(found.mac);
                     }
                     changes = true;
                 }
 
                 // Update virtual_mac if changed
                 if found.virtual_mac.is_some() && existing.virtual_mac != found.virtual_mac {
-                    existing.virtual_mac = found.virtual_mac.clone();
+                    existing.virtual_mac = found.virtual_mac;
                     changes = true;
                 }
 
                 // Update vendor if changed
-                if found.vendor.is_some() && existing.vendor != found.vendor {
+                if found.vendor.is_some()
+                    && (existing.vendor != found.vendor
+                        || existing.vendor_category != found.vendor_category
+                        || existing.vendor_custom != found.vendor_custom)
+                {
                     existing.vendor = found.vendor.clone();
+                    existing.vendor_category = found.vendor_category;
+                    existing.vendor_custom = found.vendor_custom;
                     changes = true;
                 }
 
-                if found.hostname.is_some() && existing.hostname != found.hostname {
+                if found.hostname.is_some()
+                    && (existing.hostname != found.hostname
+                        || existing.hostname_source != found.hostname_source
+                        || existing.fcrdns != found.fcrdns
+                        || existing.other_hostnames != found.other_hostnames
+                        || existing.netbios != found.netbios)
+                {
                     existing.hostname = found.hostname.clone();
+                    existing.hostname_source = found.hostname_source;
+                    existing.fcrdns = found.fcrdns;
+                    existing.other_hostnames = found.other_hostnames.clone();
+                    existing.netbios = found.netbios.clone();
+                    changes = true;
+                }
+
+                if found.upnp.is_some() && existing.upnp != found.upnp {
+                    existing.upnp = found.upnp.clone();
+                    changes = true;
+                }
+
+                // Devices don't always answer every browse or GET, only replace with a fresh answer
+                if !found.services.is_empty() && existing.services != found.services {
+                    existing.services = found.services.clone();
+                    changes = true;
+                }
+                if found.snmp.is_some() && existing.snmp != found.snmp {
+                    existing.snmp = found.snmp.clone();
+                    changes = true;
+                }
+                if found.dhcp_client.is_some() && existing.dhcp_client != found.dhcp_client {
+                    existing.dhcp_client = found.dhcp_client.clone();
+                    changes = true;
+                }
+                if found.device_type.is_some() && existing.device_type != found.device_type {
+                    existing.device_type = found.device_type;
+                    changes = true;
+                }
+                if !found.open_ports.is_empty() && existing.open_ports != found.open_ports {
+                    existing.open_ports = found.open_ports.clone();
+                    changes = true;
+                }
+                // Classify on everything known about the device, not just this scan's answers
+                let classification = classify::infer_class(existing);
+                if existing.classification != classification {
+                    existing.classification = classification;
                     changes = true;
                 }
-                existing.last_seen = Utc::now();
+
+                // Keep the IP/MAC the device used until now instead of overwriting it
+                let now = Utc::now();
+                changes |= existing.record_binding(previous_binding, now);
+                existing.sightings += 1;
+                existing.last_seen = now;
             } else {
                 // New Device
                 notify(
@@ -229,224 +422,280 @@ pub async fn run_monitor(interface: Option<String>, range: String, interval: u64
                 );
 
                 let mut new_device = found.clone();
-                if is_blocked {
-                    new_device.status = DeviceStatus::Block;
+                new_device.status = state::first_status(is_blocked, conflict_ips.contains(&found.ip));
+                // A MAC seen before by a device that has moved on to other MACs
+                let mut suffix = 1;
+                while device_map.contains_key(&new_device.id) {
+                    suffix += 1;
+                    new_device.id = format!("{}-{}", Device::id_from_mac(new_device.mac), suffix);
                 }
-                device_map.insert(new_device.ip.clone(), new_device);
+                seen_ids.insert(new_device.id.clone());
+                device_map.insert(new_device.id.clone(), new_device);
                 changes = true;
             }
         }
 
+        // Apply DHCP requests sniffed before these devices were found
+        pending_clients.retain(|mac, info| match find_by_mac(&mut device_map, *mac) {
+            Some(device) => {
+                changes |= dhcp_sniff::apply_client(device, info.clone());
+                false
+            }
+            None => true,
+        });
+
         // 2. Check for Offline Devices
         let now = Utc::now();
-        for device in device_map.values_mut() {
+        let threshold = chrono::Duration::seconds((interval as i64 * 2) + (interval as i64 / 2));
+        for device in device_map.values_mut().filter(|d| !seen_ids.contains(&d.id)) {
             let is_blocked = blacklist.contains(&device.mac);
+            let sighting = if now.signed_duration_since(device.last_seen) > threshold {
+                Sighting::Gone
+            } else {
+                Sighting::Missed
+            };
 
-            // If blocked, ensure status is Block
-            if is_blocked {
-                if device.status != DeviceStatus::Block {
-                    device.status = DeviceStatus::Block;
-                    changes = true;
-                }
-                continue; // Skip offline check for blocked devices
-            }
-
-            if device.status == DeviceStatus::Online {
-                let threshold =
-                    chrono::Duration::seconds((interval as i64 * 2) + (interval as i64 / 2));
-                if now.signed_duration_since(device.last_seen) > threshold {
-                    notify("Device Offline", &format!("{} went offline", device.ip));
-                    device.status = DeviceStatus::Offline;
-                    changes = true;
-                }
+            let previous_status = device.status;
+            if let Some((summary, body)) = state::transition(device, sighting, is_blocked, now, &config.states) {
+                notify(summary, &body);
             }
+            changes |= device.status != previous_status;
         }
 
-        if changes {
-            let devices: Vec<Device> = device_map.values().cloned().collect();
-            if let Err(e) = save_devices(&devices) {
-                eprintln!("Failed to save devices: {}", e);
-            }
+        if changes
+            && let Err(e) = save_map(&mut device_map)
+        {
+            eprintln!("Failed to save devices: {}", e);
         }
 
         // 3. Display Table
-        // Clear screen and move to top
-        print!("\x1B[2J\x1B[1;1H");
-
-        // Calculate statistics first (moved up to use in header)
-        let mut devices: Vec<&Device> = device_map.values().collect();
-        let total = devices.len();
-        let online = devices.iter().filter(|d| d.status == DeviceStatus::Online).count();
-        let offline = devices.iter().filter(|d| d.status == DeviceStatus::Offline).count();
-        let vms = devices.iter().filter(|d| {
-            if let Some(vendor) = &d.vendor {
-                vendor.contains("Virtual") || vendor.contains("Proxmox") ||
-                vendor.contains("QEMU") || vendor.contains("VMware") ||
-                vendor.contains("Hyper-V") || vendor.contains("VirtualBox")
-            } else {
-                false
-            }
-        }).count();
-
-        // Create title with stats aligned to the right
-        let title_str = format!("Network Monitor - Range: {}", range);
-        let stats_str = format!("Online: {} | Offline: {} | VMs/Containers: {} | Total: {}",
-            online, offline, vms, total);
-        let total_width: usize = 130;
-        let padding = total_width.saturating_sub(title_str.len() + stats_str.len());
-
-        println!("{}{}{}",
-            title_str.green().bold(),
-            " ".repeat(padding),
-            stats_str
-        );
+        last_scan = Utc::now();
+        print_table(&device_map, &range, &tags, last_scan);
+    }
+}
 
-        // Create last scan with version aligned to the right
-        let last_scan_str = format!("Last Scan: {}", Utc::now().format("%H:%M:%S"));
-        let version_str = format!("v{}", env!("CARGO_PKG_VERSION"));
-        let total_width: usize = 130;
-        let padding = total_width.saturating_sub(last_scan_str.len() + version_str.len());
+/// Device using `mac` as its real, virtual or an earlier randomized MAC
+fn find_by_mac(device_map: &mut HashMap<String, Device>, mac: MacAddress) -> Option<&mut Device> {
+    device_map.values_mut().find(|d| d.has_mac(mac))
+}
 
-        println!("{}{}{}", last_scan_str, " ".repeat(padding), version_str);
-        println!("{}", "-".repeat(130));
-        println!(
-            "{:<15} {:<17} {:<17} {:<20} {:<10} {:<30}",
-            "IP".bright_white().bold(),
-            "MAC".bright_white().bold(),
-            "VIRTUAL MAC".bright_white().bold(),
-            "HOSTNAME".bright_white().bold(),
-            "STATUS".bright_white().bold(),
-            "VENDOR".bright_white().bold()
-        );
-        println!("{}", "-".repeat(130));
-        // Sort by IP
-        devices.sort_by(|a, b| {
-            let ip_a =
-                a.ip.parse::<std::net::Ipv4Addr>()
-                    .unwrap_or(std::net::Ipv4Addr::new(0, 0, 0, 0));
-            let ip_b =
-                b.ip.parse::<std::net::Ipv4Addr>()
-                    .unwrap_or(std::net::Ipv4Addr::new(0, 0, 0, 0));
-            ip_a.cmp(&ip_b)
-        });
+/// Wait for the next message of a background watcher, or forever when it isn't running
+async fn next_event<T>(events: &mut Option<UnboundedReceiver<T>>) -> Option<T> {
+    match events {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
 
-        for device in &devices {
-            let hostname = device.hostname.as_deref().unwrap_or("").trim_start_matches('_');
-            let vendor_display = device.vendor.as_deref().unwrap_or("");
-
-            // Determine if this is a virtual device
-            let is_virtual = device.virtual_mac.is_some() ||
-                vendor_display.contains("Virtual") ||
-                vendor_display.contains("Proxmox") ||
-                vendor_display.contains("QEMU") ||
-                vendor_display.contains("VMware") ||
-                vendor_display.contains("Hyper-V") ||
-                vendor_display.contains("VirtualBox");
-
-            // Status coloring: Bright green (online), Orange/Yellow (offline), Red bold (blocked)
-            let status_str = device.status.to_string();
-            let status_colored = match device.status {
-                DeviceStatus::Online => status_str.bright_green(),
-                DeviceStatus::Offline => status_str.yellow(),
-                DeviceStatus::Block => status_str.red().bold(),
-            };
+/// Redraw the device table
+fn print_table(device_map: &HashMap<String, Device>, range: &str, tags: &[String], last_scan: DateTime<Utc>) {
+    // Clear screen and move to top
+    print!("\x1B[2J\x1B[1;1H");
+
+    // Calculate statistics first (moved up to use in header)
+    let mut devices: Vec<&Device> = device_map.values().filter(|d| d.matches_tags(tags)).collect();
+    let total = devices.len();
+    // Flapping, conflicting and quarantined devices count as online, expected-offline ones as
+    // offline, so the two add up to the total
+    let online = devices.iter().filter(|d| d.status.is_online()).count();
+    let offline = total - online;
+    let vms = devices.iter().filter(|d| d.is_virtual()).count();
+    // Devices per class, e.g. "RTR 1, PC 4, PHN 6", classes without devices left out
+    let classes = DeviceClass::ALL
+        .iter()
+        .filter_map(|&class| {
+            let count = devices.iter().filter(|d| d.class().is_some_and(|c| c.class == class)).count();
+            (count > 0).then(|| format!("{} {}", class.abbreviation(), count))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
 
-            // MAC display logic with colors (Palette 1 - Professional Soft):
-            // - Real MAC (physical): Bright Green (healthy hardware)
-            // - Real MAC (VM with mapping): Blue Bold (consistent with VM theme)
-            // - Empty (virtual only): Empty string
-            // - Virtual MAC: Bright Yellow (soft highlight)
-
-            // Check if the current MAC in device.mac is virtual
-            let current_mac_is_virtual = vendor_display.contains("Virtual") ||
-                vendor_display.contains("Private");
-
-            let (mac_display, virtual_mac_display) = if let Some(ref vmac) = device.virtual_mac {
-                if &device.mac == vmac {
-                    // Virtual MAC only, no real MAC known
-                    (String::new(), vmac.bright_yellow().to_string())
-                } else if current_mac_is_virtual {
-                    // Both MACs are virtual (device.mac is the most recent)
-                    // Show only the most recent virtual MAC
-                    (String::new(), device.mac.bright_yellow().to_string())
-                } else {
-                    // device.mac is real, vmac is virtual (proper mapping)
-                    (device.mac.blue().bold().to_string(), vmac.bright_yellow().to_string())
-                }
-            } else {
-                // No virtual_mac field set
-                if current_mac_is_virtual {
-                    // MAC is virtual but wasn't moved to virtual_mac field yet
-                    (String::new(), device.mac.bright_yellow().to_string())
-                } else {
-                    // No virtual MAC - physical device
-                    (device.mac.bright_green().to_string(), String::new())
-                }
-            };
+    // Create title with stats aligned to the right
+    let title_str = if tags.is_empty() {
+        format!("Network Monitor - Range: {}", range)
+    } else {
+        format!("Network Monitor - Range: {} - Tags: {}", range, tags.join(", "))
+    };
+    let stats_str = if classes.is_empty() {
+        format!("Online: {} | Offline: {} | VMs/Containers: {} | Total: {}",
+            online, offline, vms, total)
+    } else {
+        format!("Online: {} | Offline: {} | VMs/Containers: {} | {} | Total: {}",
+            online, offline, vms, classes, total)
+    };
+    let total_width: usize = 160;
+    let padding = total_width.saturating_sub(title_str.len() + stats_str.len());
 
-            // Hostname coloring: bright white if set, empty if not available
-            let hostname_colored = if hostname.is_empty() {
-                String::new()
-            } else {
-                hostname.bright_white().to_string()
-            };
+    println!("{}{}{}",
+        title_str.green().bold(),
+        " ".repeat(padding),
+        stats_str
+    );
 
-            // Vendor coloring (Palette 1 - Professional Soft):
-            // - Virtual/VM: Blue (consistent with VM theme)
-            // - Known physical: White
-            // - Unknown: Empty
-            let vendor_truncated = vendor_display.chars().take(30).collect::<String>();
-            let vendor_colored = if is_virtual {
-                vendor_truncated.blue().to_string()
-            } else if vendor_display.is_empty() {
-                String::new()
-            } else {
-                vendor_truncated.white().to_string()
-            };
+    // Create last scan with version aligned to the right
+    let last_scan_str = format!("Last Scan: {}", last_scan.format("%H:%M:%S"));
+    let version_str = format!("v{}", env!("CARGO_PKG_VERSION"));
+    let total_width: usize = 160;
+    let padding = total_width.saturating_sub(last_scan_str.len() + version_str.len());
 
-            // Use custom padding for colored strings to fix alignment
-            // CRITICAL: Always display device.ip - apply color after ensuring it's not empty
-            let final_ip = if device.ip.is_empty() {
-                "UNKNOWN".to_string()
+    println!("{}{}{}", last_scan_str, " ".repeat(padding), version_str);
+    println!("{}", "-".repeat(160));
+    println!(
+        "{:<15} {:<17} {:<17} {:<20} {:<7} {:<16} {:<5} {:<30} {}",
+        "IP".bright_white().bold(),
+        "MAC".bright_white().bold(),
+        "VIRTUAL MAC".bright_white().bold(),
+        "HOSTNAME".bright_white().bold(),
+        "SRC".bright_white().bold(),
+        "STATUS".bright_white().bold(),
+        "CLASS".bright_white().bold(),
+        "VENDOR".bright_white().bold(),
+        "MODEL".bright_white().bold()
+    );
+    println!("{}", "-".repeat(160));
+    // Sort by IP
+    devices.sort_by_key(|d| d.ip);
+
+    for device in &devices {
+        // The alias replaces the hostname
+        let (name, source) = device.name_and_source();
+        let hostname = name.unwrap_or("").trim_start_matches('_');
+        let vendor_display = device.vendor.as_deref().unwrap_or("");
+
+        // Determine if this is a virtual device
+        let is_virtual = device.is_virtual();
+
+        // Status coloring: Bright green (online), Orange/Yellow (offline), Red bold (blocked),
+        // magenta (flapping, quarantined), bright red (IP conflict), cyan (expected offline)
+        let status_colored = device.status.colored();
+
+        // MAC display logic with colors (Palette 1 - Professional Soft):
+        // - Real MAC (physical): Bright Green (healthy hardware)
+        // - Real MAC (VM with mapping): Blue Bold (consistent with VM theme)
+        // - Empty (virtual only): Empty string
+        // - Virtual MAC: Bright Yellow (soft highlight)
+
+        // Check if the current MAC in device.mac is virtual
+        let current_mac_is_virtual = device
+            .vendor_category
+            .is_some_and(VendorCategory::is_virtual);
+
+        let mac = device.mac.to_string();
+        let (mac_display, virtual_mac_display) = if let Some(vmac) = device.virtual_mac {
+            if device.mac == vmac {
+                // Virtual MAC only, no real MAC known
+                (String::new(), vmac.to_string().bright_yellow().to_string())
+            } else if current_mac_is_virtual {
+                // Both MACs are virtual (device.mac is the most recent)
+                // Show only the most recent virtual MAC
+                (String::new(), mac.bright_yellow().to_string())
             } else {
-                device.ip.clone()
-            };
-
-            let ip_to_display = if is_virtual {
-                final_ip.blue().to_string()
+                // device.mac is real, vmac is virtual (proper mapping)
+                (mac.blue().bold().to_string(), vmac.to_string().bright_yellow().to_string())
+            }
+        } else {
+            // No virtual_mac field set
+            if current_mac_is_virtual {
+                // MAC is virtual but wasn't moved to virtual_mac field yet
+                (String::new(), mac.bright_yellow().to_string())
             } else {
-                final_ip
-            };
+                // No virtual MAC - physical device
+                (mac.bright_green().to_string(), String::new())
+            }
+        };
 
-            // Use custom padding for colored strings to fix alignment
-            println!(
-                "{} {} {} {} {} {}",
-                pad_colored(ip_to_display, 15),
-                pad_colored(mac_display, 17),
-                pad_colored(virtual_mac_display, 17),
-                pad_colored(hostname_colored.chars().take(20).collect::<String>(), 20),
-                pad_colored(status_colored.to_string(), 10),
-                vendor_colored.chars().take(30).collect::<String>()
-            );
-        }
+        // Hostname source (dhcp, dns, mdns, ..., or alias): dimmed, empty if unknown
+        let source_display = match (source, hostname.is_empty()) {
+            (Some(source), false) => source.dimmed().to_string(),
+            _ => String::new(),
+        };
 
-        println!("{}", "-".repeat(130));
-
-        // Color legend (Palette 1 - Professional Soft) - Footer
-        println!("{}", "-".repeat(130));
-        println!("{}: {} {} | {} {} {} {} | {} {}",
-            "Legend".bright_white().bold(),
-            "IP:".dimmed(), format!("{} Physical", "□".white()).dimmed(),
-            format!("{} VM/Virtual", "□".blue()),
-            "|".dimmed(),
-            "MAC:".dimmed(), format!("{} Physical", "□".bright_green()).dimmed(),
-            format!("{} VM Real", "□".blue().bold()),
-            format!("{} VM Virtual", "□".bright_yellow())
+        // Hostname coloring: bright white if set, empty if not available
+        let hostname_colored = if hostname.is_empty() {
+            String::new()
+        } else {
+            hostname.bright_white().to_string()
+        };
+
+        // Vendor coloring (Palette 1 - Professional Soft):
+        // - Virtual/VM: Blue (consistent with VM theme)
+        // - Known physical: White
+        // - Unknown: Empty
+        // Vendors from custom_vendors.json end with "*"
+        let vendor_truncated = if device.vendor_custom {
+            format!("{}*", vendor_display.chars().take(29).collect::<String>())
+        } else {
+            vendor_display.chars().take(30).collect::<String>()
+        };
+        let vendor_colored = if is_virtual {
+            vendor_truncated.blue().to_string()
+        } else if vendor_display.is_empty() {
+            String::new()
+        } else {
+            vendor_truncated.white().to_string()
+        };
+
+        let final_ip = device.ip.to_string();
+        let ip_to_display = if is_virtual {
+            final_ip.blue().to_string()
+        } else {
+            final_ip
+        };
+
+        // UPnP friendly name and model, or the device type from its services,
+        // dimmed like other secondary details
+        let model_display = device
+            .upnp
+            .as_ref()
+            .map(|upnp| upnp.summary())
+            .or_else(|| device.device_type.map(|t| t.to_string()))
+            .map(|model| model.chars().take(30).collect::<String>().dimmed().to_string())
+            .unwrap_or_default();
+
+        // Device class abbreviation: cyan, dimmed when the guess is weak (under 50%)
+        let class_display = match device.class() {
+            Some(c) if c.confidence >= 50 => c.class.abbreviation().cyan().to_string(),
+            Some(c) => c.class.abbreviation().dimmed().to_string(),
+            None => String::new(),
+        };
+
+        // Use custom padding for colored strings to fix alignment
+        println!(
+            "{} {} {} {} {} {} {} {} {}",
+            pad_colored(ip_to_display, 15),
+            pad_colored(mac_display, 17),
+            pad_colored(virtual_mac_display, 17),
+            pad_colored(hostname_colored.chars().take(20).collect::<String>(), 20),
+            pad_colored(source_display, 7),
+            pad_colored(status_colored.to_string(), 16),
+            pad_colored(class_display, 5),
+            pad_colored(vendor_colored, 30),
+            model_display
         );
     }
+
+    println!("{}", "-".repeat(160));
+
+    // Color legend (Palette 1 - Professional Soft) - Footer
+    println!("{}", "-".repeat(160));
+    let ip_physical = format!("{} Physical", "□".white());
+    let mac_physical = format!("{} Physical", "□".bright_green());
+    println!("{}: {} {} | {} VM/Virtual {} {} {} | {} VM Real | {} VM Virtual {} {} {} {}",
+        "Legend".bright_white().bold(),
+        "IP:".dimmed(), ip_physical.dimmed(),
+        "□".blue(),
+        "|".dimmed(),
+        "MAC:".dimmed(), mac_physical.dimmed(),
+        "□".blue().bold(),
+        "□".bright_yellow(),
+        "|".dimmed(),
+        "* Custom vendor".dimmed(),
+        "|".dimmed(),
+        "Dimmed class: low confidence".dimmed()
+    );
 }
 
-fn load_devices() -> io::Result<Vec<Device>> {
+pub fn load_devices() -> io::Result<Vec<Device>> {
     if !Path::new(STORAGE_FILE).exists() {
         return Ok(Vec::new());
     }
@@ -459,25 +708,71 @@ fn load_devices() -> io::Result<Vec<Device>> {
         e
     })?;
 
-    let reader = BufReader::new(&file);
-    let devices: Vec<Device> = serde_json::from_reader(reader)?;
+    let devices = read_devices(&file);
 
     // Lock is automatically released when file goes out of scope
     file.unlock().ok();
 
-    // Validate all loaded devices and filter out invalid ones
-    let total_count = devices.len();
-    let valid_devices: Vec<Device> = devices
+    devices
+}
+
+/// Read devices.json, let `change` edit the devices and write them back if it returns
+/// `Some`, all under one exclusive lock: the monitor and the `edit` command both rewrite the
+/// file, and neither may lose what the other saved in between
+pub fn update_devices<T>(change: impl FnOnce(&mut Vec<Device>) -> Option<T>) -> io::Result<Option<T>> {
+    // Not truncated before the lock is held: that would wipe the file under a reader
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(STORAGE_FILE)?;
+
+    // Acquire exclusive lock for writing
+    file.lock_exclusive().map_err(|e| {
+        eprintln!("Failed to acquire write lock on {}: {}", STORAGE_FILE, e);
+        eprintln!("This may indicate multiple instances of the monitor are running.");
+        eprintln!("Please ensure only one instance is running at a time.");
+        e
+    })?;
+
+    let mut devices = read_devices(&file)?;
+    let result = change(&mut devices);
+    if result.is_some() {
+        // Serialized first, so the file is only emptied once the new content is ready
+        let content = serde_json::to_vec_pretty(&devices)?;
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&content)?;
+    }
+
+    // Explicitly unlock before closing
+    file.unlock().ok();
+
+    Ok(result)
+}
+
+fn read_devices(file: &File) -> io::Result<Vec<Device>> {
+    // Just created by update_devices
+    if file.metadata()?.len() == 0 {
+        return Ok(Vec::new());
+    }
+
+    let reader = BufReader::new(file);
+    let entries: Vec<serde_json::Value> = serde_json::from_reader(reader)?;
+
+    // Parse each device on its own so one entry with a bad IP or MAC doesn't lose the rest
+    let total_count = entries.len();
+    let valid_devices: Vec<Device> = entries
         .into_iter()
-        .filter(|d| {
-            let is_valid = d.validate();
-            if !is_valid {
-                eprintln!(
-                    "Skipping corrupted device from {}: IP='{}', MAC='{}'",
-                    STORAGE_FILE, d.ip, d.mac
-                );
+        .filter_map(|entry| match serde_json::from_value::<Device>(entry) {
+            Ok(mut device) => {
+                // Saved by earlier versions
+                if device.id.is_empty() {
+                    device.id = Device::id_from_mac(device.mac);
+                }
+                device.first_seen.get_or_insert(device.last_seen);
+                Some(device)
+            }
+            Err(e) => {
+                eprintln!("Skipping corrupted device from {}: {}", STORAGE_FILE, e);
+                None
             }
-            is_valid
         })
         .collect();
 
@@ -492,7 +787,7 @@ fn load_devices() -> io::Result<Vec<Device>> {
     Ok(valid_devices)
 }
 
-fn load_blacklist() -> io::Result<Vec<String>> {
+fn load_blacklist() -> io::Result<Vec<MacAddress>> {
     if !Path::new(BLACKLIST_FILE).exists() {
         return Ok(Vec::new());
     }
@@ -506,34 +801,45 @@ fn load_blacklist() -> io::Result<Vec<String>> {
     })?;
 
     let reader = BufReader::new(&file);
-    let blacklist: Vec<String> = serde_json::from_reader(reader)?;
+    let entries: Vec<String> = serde_json::from_reader(reader)?;
 
     // Lock is automatically released when file goes out of scope
     file.unlock().ok();
 
+    // Any MAC notation works, entries that aren't MACs are reported and ignored
+    let blacklist = entries
+        .iter()
+        .filter_map(|entry| {
+            entry
+                .parse()
+                .map_err(|e| eprintln!("Warning: ignoring entry in {}: {}", BLACKLIST_FILE, e))
+                .ok()
+        })
+        .collect();
+
     Ok(blacklist)
 }
 
-fn save_devices(devices: &[Device]) -> io::Result<()> {
-    let file = File::create(STORAGE_FILE)?;
-
-    // Acquire exclusive lock for writing
-    file.lock_exclusive().map_err(|e| {
-        eprintln!("Failed to acquire write lock on {}: {}", STORAGE_FILE, e);
-        eprintln!("This may indicate multiple instances of the monitor are running.");
-        eprintln!("Please ensure only one instance is running at a time.");
-        e
+/// Save the devices, keeping the user details edited in devices.json while the monitor runs
+fn save_map(device_map: &mut HashMap<String, Device>) -> io::Result<()> {
+    update_devices(|devices| {
+        sync_user_info(device_map, devices);
+        *devices = device_map.values().cloned().collect();
+        Some(())
     })?;
-
-    let writer = BufWriter::new(&file);
-    serde_json::to_writer_pretty(writer, devices)?;
-
-    // Explicitly unlock before closing
-    file.unlock().ok();
-
     Ok(())
 }
 
+/// Take the alias, owner, tags, notes and location from the saved devices, the `edit`
+/// command changes them there
+fn sync_user_info(device_map: &mut HashMap<String, Device>, saved: &mut [Device]) {
+    for saved in saved {
+        if let Some(device) = device_map.get_mut(&saved.id) {
+            device.user = std::mem::take(&mut saved.user);
+        }
+    }
+}
+
 fn notify(summary: &str, body: &str) {
     let _ = Notification::new().summary(summary).body(body).show();
 }
@@ -0,0 +1,213 @@
+//! NetBIOS node status (NBSTAT) queries over UDP 137 (RFC 1002, section 4.2.18)
+//! Replaces shelling out to Samba's `nmblookup -A`
+
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+use crate::mac::MacAddress;
+
+pub const NETBIOS_PORT: u16 = 137;
+
+/// How long to wait for a node status response
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+const TYPE_NBSTAT: u16 = 0x0021;
+const CLASS_IN: u16 = 0x0001;
+
+/// Name flag marking a group (workgroup/domain) name
+const GROUP_NAME_FLAG: u16 = 0x8000;
+
+/// Suffix for workstation and workgroup names
+const SUFFIX_WORKSTATION: u8 = 0x00;
+
+/// Result of a node status query
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    /// Unique name with the workstation suffix (computer name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Group name with the workstation suffix (workgroup or domain)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workgroup: Option<String>,
+    /// MAC address reported by the host (None when it reports all zeros, as Samba does)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac: Option<MacAddress>,
+}
+
+impl NodeStatus {
+    /// Checks the reported MAC against the one seen via ARP
+    /// Returns false only when both are known and differ
+    pub fn mac_matches(&self, arp_mac: MacAddress) -> bool {
+        self.mac.is_none_or(|mac| mac == arp_mac)
+    }
+}
+
+/// Send a node status request to `ip` and parse the answer
+pub async fn node_status(ip: Ipv4Addr, wait: Duration) -> Option<NodeStatus> {
+    let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .ok()?;
+
+    let octets = ip.octets();
+    let transaction_id = (std::process::id() as u16) ^ u16::from_be_bytes([octets[2], octets[3]]);
+    let query = build_nbstat_query(transaction_id);
+    socket
+        .send_to(&query, SocketAddrV4::new(ip, NETBIOS_PORT))
+        .await
+        .ok()?;
+
+    let mut buf = [0u8; 1024];
+    let result = timeout(wait, async {
+        loop {
+            let (len, from) = socket.recv_from(&mut buf).await.ok()?;
+            if from.ip() != std::net::IpAddr::V4(ip) {
+                continue;
+            }
+            if let Some(status) = parse_nbstat_response(&buf[..len], transaction_id) {
+                return Some(status);
+            }
+        }
+    })
+    .await;
+
+    result.ok().flatten()
+}
+
+fn build_nbstat_query(transaction_id: u16) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(50);
+    buf.extend_from_slice(&transaction_id.to_be_bytes());
+    buf.extend_from_slice(&0u16.to_be_bytes()); // flags: query
+    buf.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    buf.extend_from_slice(&[0; 6]); // ANCOUNT, NSCOUNT, ARCOUNT
+    buf.extend_from_slice(&encode_name(b"*"));
+    buf.extend_from_slice(&TYPE_NBSTAT.to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    buf
+}
+
+/// First-level encoding of a NetBIOS name: padded to 16 bytes, each nibble mapped to 'A'..'P'
+fn encode_name(name: &[u8]) -> Vec<u8> {
+    let mut padded = [0u8; 16];
+    padded[..name.len()].copy_from_slice(name);
+
+    let mut encoded = Vec::with_capacity(34);
+    encoded.push(32);
+    for byte in padded {
+        encoded.push(b'A' + (byte >> 4));
+        encoded.push(b'A' + (byte & 0x0F));
+    }
+    encoded.push(0);
+    encoded
+}
+
+fn parse_nbstat_response(buf: &[u8], transaction_id: u16) -> Option<NodeStatus> {
+    if buf.len() < 12 || u16::from_be_bytes([buf[0], buf[1]]) != transaction_id {
+        return None;
+    }
+    // Must be a response with at least one answer
+    if buf[2] & 0x80 == 0 || u16::from_be_bytes([buf[6], buf[7]]) == 0 {
+        return None;
+    }
+
+    // Skip the answer name (either a full encoded name or a compression pointer)
+    let mut pos = 12;
+    loop {
+        let len = *buf.get(pos)? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+        if len & 0xC0 == 0xC0 {
+            pos += 2;
+            break;
+        }
+        pos += 1 + len;
+    }
+
+    // TYPE, CLASS, TTL, RDLENGTH
+    let rtype = u16::from_be_bytes([*buf.get(pos)?, *buf.get(pos + 1)?]);
+    if rtype != TYPE_NBSTAT {
+        return None;
+    }
+    pos += 10;
+
+    let num_names = *buf.get(pos)? as usize;
+    pos += 1;
+
+    let mut status = NodeStatus::default();
+    for _ in 0..num_names {
+        let entry = buf.get(pos..pos + 18)?;
+        let name = String::from_utf8_lossy(&entry[..15]).trim_end().to_string();
+        let suffix = entry[15];
+        let flags = u16::from_be_bytes([entry[16], entry[17]]);
+        pos += 18;
+
+        if suffix != SUFFIX_WORKSTATION || name.is_empty() {
+            continue;
+        }
+        if flags & GROUP_NAME_FLAG != 0 {
+            status.workgroup.get_or_insert(name);
+        } else {
+            status.name.get_or_insert(name);
+        }
+    }
+
+    // Statistics block starts with the 6-byte unit ID (MAC address)
+    if let Some(unit_id) = buf.get(pos..pos + 6)
+        && unit_id.iter().any(|&b| b != 0)
+    {
+        status.mac = <[u8; 6]>::try_from(unit_id).ok().map(MacAddress::new);
+    }
+
+    Some(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_entry(name: &str, suffix: u8, flags: u16) -> Vec<u8> {
+        let mut entry = format!("{:<15}", name).into_bytes();
+        entry.push(suffix);
+        entry.extend_from_slice(&flags.to_be_bytes());
+        entry
+    }
+
+    #[test]
+    fn test_encode_wildcard_name() {
+        let encoded = encode_name(b"*");
+        assert_eq!(encoded.len(), 34);
+        assert_eq!(&encoded[1..3], b"CK");
+        assert!(encoded[3..33].iter().all(|&b| b == b'A'));
+    }
+
+    #[test]
+    fn test_parse_node_status() {
+        let mut packet = vec![0x12, 0x34, 0x84, 0x00, 0, 0, 0, 1, 0, 0, 0, 0];
+        packet.extend_from_slice(&encode_name(b"*"));
+        packet.extend_from_slice(&TYPE_NBSTAT.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0]); // TTL
+
+        let mut rdata = vec![3];
+        rdata.extend(name_entry("DESKTOP-01", 0x00, 0x0400));
+        rdata.extend(name_entry("WORKGROUP", 0x00, 0x8400));
+        rdata.extend(name_entry("DESKTOP-01", 0x20, 0x0400));
+        rdata.extend_from_slice(&[0x68, 0x5b, 0x35, 0x8d, 0x89, 0x41]);
+        rdata.extend_from_slice(&[0; 40]);
+        packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        packet.extend(rdata);
+
+        let status = parse_nbstat_response(&packet, 0x1234).unwrap();
+        assert_eq!(status.name.as_deref(), Some("DESKTOP-01"));
+        assert_eq!(status.workgroup.as_deref(), Some("WORKGROUP"));
+        assert_eq!(status.mac, "68:5b:35:8d:89:41".parse().ok());
+        assert!(status.mac_matches("68:5B:35:8D:89:41".parse().unwrap()));
+        assert!(!status.mac_matches("68:5B:35:8D:89:42".parse().unwrap()));
+
+        assert!(parse_nbstat_response(&packet, 0x4321).is_none());
+    }
+}
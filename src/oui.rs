@@ -0,0 +1,236 @@
+//! Readers for MAC vendor registries: the IEEE exports (oui.txt, oui.csv, mam.csv, oui36.csv)
+//! and Wireshark's manuf file, plus the local cache filled by `vendor update`
+//!
+//! Prefixes are kept as uppercase hex digits without separators, so their length gives the
+//! block size: 6 digits for MA-L (24 bits), 7 for MA-M (28 bits), 9 for MA-S/IAB (36 bits)
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::registry::csv_fields;
+
+/// Imported registry entries, stored next to devices.json
+pub const OUI_CACHE_FILE: &str = "oui_cache.json";
+
+/// Probed when no registry path is configured (Debian's ieee-data package and Wireshark)
+const DEFAULT_REGISTRY_PATHS: [&str; 2] = ["/usr/share/ieee-data", "/usr/share/wireshark/manuf"];
+
+/// Registry files read from a registry directory, broadest blocks first
+const REGISTRY_FILE_NAMES: [&str; 6] = ["oui.txt", "oui.csv", "mam.csv", "oui36.csv", "iab.csv", "manuf"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VendorConfig {
+    /// Registry file, or directory holding oui.txt/oui.csv/mam.csv/oui36.csv/manuf
+    /// Common system locations are probed when unset
+    pub registry_path: Option<PathBuf>,
+}
+
+impl VendorConfig {
+    fn registry_paths(&self) -> Vec<PathBuf> {
+        match &self.registry_path {
+            Some(path) => vec![path.clone()],
+            None => DEFAULT_REGISTRY_PATHS.iter().map(PathBuf::from).collect(),
+        }
+    }
+}
+
+/// Prefix (hex digits) -> organization name
+pub type Registry = BTreeMap<String, String>;
+
+/// Entries from the configured registry path, then the local cache (which wins on conflicts)
+pub fn load(config: &VendorConfig) -> Registry {
+    let mut registry = Registry::new();
+
+    for path in config.registry_paths() {
+        let files = if path.is_dir() {
+            REGISTRY_FILE_NAMES.iter().map(|name| path.join(name)).collect()
+        } else {
+            vec![path]
+        };
+        for file in files.iter().filter(|f| f.is_file()) {
+            match fs::read_to_string(file) {
+                Ok(content) => registry.extend(parse_registry(&content)),
+                Err(e) => eprintln!("Warning: cannot read {}: {}", file.display(), e),
+            }
+        }
+    }
+
+    match load_cache() {
+        Ok(cache) => registry.extend(cache),
+        Err(e) => eprintln!("Warning: ignoring {}: {}", OUI_CACHE_FILE, e),
+    }
+
+    registry
+}
+
+/// Import a downloaded registry file into the local cache
+/// Returns the number of entries read from the file and the cache size afterwards
+pub fn import(path: &Path) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+    let entries = parse_registry(&fs::read_to_string(path)?);
+    if entries.is_empty() {
+        return Err(format!("no registry entries found in {}", path.display()).into());
+    }
+
+    let mut cache = load_cache()?;
+    let imported = entries.len();
+    cache.extend(entries);
+    save_cache(&cache)?;
+    Ok((imported, cache.len()))
+}
+
+fn load_cache() -> io::Result<Registry> {
+    if !Path::new(OUI_CACHE_FILE).exists() {
+        return Ok(Registry::new());
+    }
+    let file = File::open(OUI_CACHE_FILE)?;
+    file.lock_shared()?;
+    let reader = BufReader::new(&file);
+    let cache = serde_json::from_reader(reader)?;
+    file.unlock().ok();
+    Ok(cache)
+}
+
+fn save_cache(cache: &Registry) -> io::Result<()> {
+    let file = File::create(OUI_CACHE_FILE)?;
+    file.lock_exclusive()?;
+    let writer = BufWriter::new(&file);
+    serde_json::to_writer_pretty(writer, cache)?;
+    file.unlock().ok();
+    Ok(())
+}
+
+/// Parse any supported registry format, detected from the contents
+pub fn parse_registry(content: &str) -> Vec<(String, String)> {
+    let first_line = content.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    if first_line.starts_with("Registry,") {
+        parse_ieee_csv(content)
+    } else if content.contains("(hex)") {
+        parse_ieee_txt(content)
+    } else {
+        parse_manuf(content)
+    }
+}
+
+/// IEEE CSV export: `MA-L,002272,American Micro-Fuel Device Corp.,"address"`
+fn parse_ieee_csv(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = csv_fields(line);
+            let prefix = normalize_prefix(fields.get(1)?, None)?;
+            let name = fields.get(2)?.trim();
+            (!name.is_empty()).then(|| (prefix, name.to_string()))
+        })
+        .collect()
+}
+
+/// IEEE text export: `00-22-72   (hex)\t\tAmerican Micro-Fuel Device Corp.`
+fn parse_ieee_txt(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (prefix, name) = line.split_once("(hex)")?;
+            let prefix = normalize_prefix(prefix.trim(), None)?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (prefix, name.to_string()))
+        })
+        .collect()
+}
+
+/// Wireshark manuf: `00:00:0C<TAB>Cisco<TAB>Cisco Systems, Inc` or `00:1B:C5:00:00:00/36<TAB>...`
+/// The long name is used when present
+fn parse_manuf(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split('\t').map(str::trim).filter(|f| !f.is_empty());
+            let prefix = fields.next()?;
+            let short_name = fields.next()?;
+            let name = fields.next().unwrap_or(short_name);
+
+            let (prefix, bits) = match prefix.split_once('/') {
+                Some((prefix, bits)) => (prefix, Some(bits.parse::<usize>().ok()?)),
+                None => (prefix, None),
+            };
+            Some((normalize_prefix(prefix, bits)?, name.to_string()))
+        })
+        .collect()
+}
+
+/// Hex digits of a prefix in any separator style, cut to `bits` when given
+/// Only whole-nibble blocks of 24, 28 or 36 bits are kept
+fn normalize_prefix(prefix: &str, bits: Option<usize>) -> Option<String> {
+    let digits: String = prefix
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | '.'))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let len = match bits {
+        Some(bits) if bits % 4 == 0 => bits / 4,
+        Some(_) => return None,
+        None => digits.len(),
+    };
+    if !matches!(len, 6 | 7 | 9) || digits.len() < len {
+        return None;
+    }
+    Some(digits[..len].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ieee_formats() {
+        let csv = "Registry,Assignment,Organization Name,Organization Address\n\
+            MA-L,002272,American Micro-Fuel Device Corp.,2181 Buchanan Loop Ferndale WA US 98248\n\
+            MA-M,1C88797,\"Sensys Networks, Inc.\",\"Berkeley CA US\"\n\
+            MA-S,70B3D5F2C,Vision Technologies,Budapest HU\n";
+        assert_eq!(
+            parse_registry(csv),
+            vec![
+                ("002272".to_string(), "American Micro-Fuel Device Corp.".to_string()),
+                ("1C88797".to_string(), "Sensys Networks, Inc.".to_string()),
+                ("70B3D5F2C".to_string(), "Vision Technologies".to_string()),
+            ]
+        );
+
+        let txt = "OUI/MA-L\t\t\tOrganization\n\
+            company_id\t\t\tOrganization\n\n\
+            00-22-72   (hex)\t\tAmerican Micro-Fuel Device Corp.\n\
+            002272     (base 16)\t\tAmerican Micro-Fuel Device Corp.\n\
+            \t\t\t\t2181 Buchanan Loop\n";
+        assert_eq!(
+            parse_registry(txt),
+            vec![("002272".to_string(), "American Micro-Fuel Device Corp.".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_manuf() {
+        let manuf = "# Wireshark manuf\n\
+            00:00:0C\tCisco\tCisco Systems, Inc\n\
+            00:1B:C5:00:00:00/36\tConvergi\tConverging Systems Inc.\n\
+            00:55:DA:C0:00:00/28\tNanoleaf\n\
+            01:80:C2:00:00:00/48\tSTP\n";
+        assert_eq!(
+            parse_registry(manuf),
+            vec![
+                ("00000C".to_string(), "Cisco Systems, Inc".to_string()),
+                ("001BC5000".to_string(), "Converging Systems Inc.".to_string()),
+                ("0055DAC".to_string(), "Nanoleaf".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! TCP connect probes of a few well-known ports
+//! Open ports like 9100 (raw printing), 8006 (Proxmox) or 62078 (iOS sync) hint at
+//! what a device is. Off by default, since it touches every host on the network
+
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::timeout;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortsConfig {
+    pub enabled: bool,
+    /// Ports to try on every device
+    pub ports: Vec<u16>,
+    /// How long to wait for each connection, in milliseconds
+    pub timeout_ms: u64,
+    /// Connections open at once across all devices, to stay well below the file descriptor limit
+    pub max_connections: usize,
+}
+
+impl Default for PortsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            // The ports the classifier knows about
+            ports: vec![22, 53, 445, 515, 554, 631, 3389, 5001, 8006, 8009, 9100, 62078],
+            timeout_ms: 300,
+            max_connections: 256,
+        }
+    }
+}
+
+impl PortsConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// Shared limit for the probes of one scan
+    pub fn connection_limit(&self) -> Arc<Semaphore> {
+        Arc::new(Semaphore::new(self.max_connections.max(1)))
+    }
+}
+
+/// Ports of `ip` that accept a TCP connection within `wait`, sorted
+/// Each connection holds a permit of `limit` while it is open
+pub async fn open_ports(ip: Ipv4Addr, ports: &[u16], wait: Duration, limit: Arc<Semaphore>) -> Vec<u16> {
+    let mut probes = JoinSet::new();
+    for &port in ports {
+        let limit = limit.clone();
+        probes.spawn(async move {
+            let _permit = limit.acquire_owned().await.ok()?;
+            let connect = TcpStream::connect(SocketAddrV4::new(ip, port));
+            matches!(timeout(wait, connect).await, Ok(Ok(_))).then_some(port)
+        });
+    }
+
+    let mut open = Vec::new();
+    while let Some(result) = probes.join_next().await {
+        if let Ok(Some(port)) = result {
+            open.push(port);
+        }
+    }
+    open.sort_unstable();
+    open
+}
@@ -1,10 +1,13 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::net::Ipv4Addr;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
 
+use crate::mac::MacAddress;
+
 const PROXMOX_CONFIG_FILE: &str = "proxmox.json";
 const MAC_MAPPING_FILE: &str = "mac_mapping.json";
 
@@ -177,15 +180,15 @@ impl ProxmoxClient {
             // Para containers LXC e VMs QEMU, procurar por net0, net1, etc
             for i in 0..10 {
                 let net_key = format!("net{}", i);
-                if let Some(net_value) = data.get(&net_key) {
-                    if let Some(net_str) = net_value.as_str() {
-                        // Parse hwaddr from string like "name=eth0,bridge=vmbr0,hwaddr=BC:24:11:36:2D:6E,ip=dhcp,type=veth"
-                        if let Some(hwaddr) = parse_hwaddr_from_config(net_str) {
-                            interfaces.push(NetworkInterface {
-                                hwaddr: Some(hwaddr),
-                                name: format!("net{}", i),
-                            });
-                        }
+                if let Some(net_value) = data.get(&net_key)
+                    && let Some(net_str) = net_value.as_str()
+                {
+                    // Parse hwaddr from string like "name=eth0,bridge=vmbr0,hwaddr=BC:24:11:36:2D:6E,ip=dhcp,type=veth"
+                    if let Some(hwaddr) = parse_hwaddr_from_config(net_str) {
+                        interfaces.push(NetworkInterface {
+                            hwaddr: Some(hwaddr),
+                            name: format!("net{}", i),
+                        });
                     }
                 }
             }
@@ -196,7 +199,7 @@ impl ProxmoxClient {
 
     /// Returns a mapping of detected (virtual) MAC -> real MAC from Proxmox VMs/Containers
     /// This helps correct MAC addresses that are obscured by virtualization layers
-    pub async fn get_mac_correction_map(&mut self) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    pub async fn get_mac_correction_map(&mut self) -> Result<HashMap<MacAddress, MacAddress>, Box<dyn std::error::Error>> {
         // Authenticate first
         self.authenticate().await?;
 
@@ -206,7 +209,7 @@ impl ProxmoxClient {
         Ok(HashMap::new())
     }
 
-    pub async fn get_all_vm_macs(&mut self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    pub async fn get_all_vm_macs(&mut self) -> Result<Vec<MacAddress>, Box<dyn std::error::Error>> {
         // Authenticate first
         self.authenticate().await?;
 
@@ -220,9 +223,8 @@ impl ProxmoxClient {
                     .await?;
 
                 for interface in interfaces {
-                    if let Some(mac) = interface.hwaddr {
-                        let normalized_mac = normalize_mac(&mac);
-                        macs.push(normalized_mac);
+                    if let Some(mac) = interface.hwaddr.and_then(|mac| mac.parse().ok()) {
+                        macs.push(mac);
                     }
                 }
             }
@@ -243,13 +245,6 @@ fn parse_hwaddr_from_config(config_str: &str) -> Option<String> {
     None
 }
 
-fn normalize_mac(mac: &str) -> String {
-    mac.to_uppercase()
-        .replace("-", ":")
-        .chars()
-        .collect::<String>()
-}
-
 #[allow(dead_code)]
 pub fn load_proxmox_config() -> Result<ProxmoxConfig, Box<dyn std::error::Error>> {
     if !Path::new(PROXMOX_CONFIG_FILE).exists() {
@@ -271,14 +266,14 @@ pub fn save_proxmox_config_example() -> Result<(), Box<dyn std::error::Error>> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MacMapping {
-    pub ip: String,
-    pub real_mac: String,
+    pub ip: Ipv4Addr,
+    pub real_mac: MacAddress,
     pub description: Option<String>,
 }
 
 /// Load manual MAC address mappings from file
 /// Format: [{"ip": "192.168.15.31", "real_mac": "BC:24:11:36:2D:6E", "description": "Proxmox Container"}]
-pub fn load_mac_mappings() -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+pub fn load_mac_mappings() -> Result<HashMap<Ipv4Addr, MacAddress>, Box<dyn std::error::Error>> {
     if !Path::new(MAC_MAPPING_FILE).exists() {
         return Ok(HashMap::new());
     }
@@ -288,7 +283,7 @@ pub fn load_mac_mappings() -> Result<HashMap<String, String>, Box<dyn std::error
 
     let mut map = HashMap::new();
     for mapping in mappings {
-        map.insert(mapping.ip, normalize_mac(&mapping.real_mac));
+        map.insert(mapping.ip, mapping.real_mac);
     }
 
     Ok(map)
@@ -298,8 +293,8 @@ pub fn load_mac_mappings() -> Result<HashMap<String, String>, Box<dyn std::error
 pub fn save_mac_mapping_example() -> Result<(), Box<dyn std::error::Error>> {
     let example = vec![
         MacMapping {
-            ip: "192.168.15.31".to_string(),
-            real_mac: "BC:24:11:36:2D:6E".to_string(),
+            ip: Ipv4Addr::new(192, 168, 15, 31),
+            real_mac: MacAddress::new([0xbc, 0x24, 0x11, 0x36, 0x2d, 0x6e]),
             description: Some("Proxmox LXC Container".to_string()),
         },
     ];
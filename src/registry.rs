@@ -0,0 +1,64 @@
+//! Registry helpers shared with build.rs, which reads and categorizes the built-in vendor
+//! table the same way registry files are read at runtime (`vendor update`, `vendor.registry_path`)
+
+/// Keywords categorizing registry names (lowercase), checked in order; names matching none
+/// are "other". Categories are spelled as in data/vendors.csv and custom_vendors.json
+pub const CATEGORY_RULES: &[(&str, &[&str])] = &[
+    (
+        "virtualization",
+        &["vmware", "qemu", "proxmox", "xensource", "virtualbox", "parallels", "hyper-v"],
+    ),
+    (
+        "networking",
+        &[
+            "cisco", "juniper", "ubiquiti", "tp-link", "netgear", "mikrotik", "routerboard", "aruba",
+            "d-link", "zyxel", "arris", "intelbras", "fortinet", "ruckus", "extreme networks",
+        ],
+    ),
+    (
+        "mobile",
+        &["samsung", "xiaomi", "oneplus", "motorola", "huawei device", "oppo", "vivo mobile", "realme"],
+    ),
+    (
+        "iot",
+        &[
+            "raspberry", "espressif", "tuya", "shelly", "sonoff", "itead", "nest labs", "ring llc",
+            "signify", "philips lighting", "ecobee", "wyze", "sonos", "roku", "amazon technologies",
+        ],
+    ),
+    (
+        "computer",
+        &["dell", "hewlett", "lenovo", "apple", "intel", "realtek", "asustek", "acer", "microsoft", "micro-star"],
+    ),
+];
+
+/// Category of a registry organization name
+pub fn category_of(name: &str) -> &'static str {
+    let name = name.to_lowercase();
+    CATEGORY_RULES
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|k| name.contains(k)))
+        .map_or("other", |(category, _)| category)
+}
+
+/// Split a CSV line, honoring double-quoted fields
+pub fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
@@ -0,0 +1,142 @@
+//! Unicast reverse DNS (PTR) lookups against the system resolver or configured servers,
+//! with optional forward-confirmed reverse DNS (FCrDNS) checking
+
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Time allowed for a reverse lookup, including the FCrDNS forward lookup, unless the resolver
+/// chain sets one
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsConfig {
+    /// DNS servers used for reverse lookups (e.g. the local router). Empty uses the system resolver
+    pub servers: Vec<IpAddr>,
+    /// Domain suffix removed from PTR results (e.g. "lan" turns "laptop.lan" into "laptop")
+    pub strip_domain: Option<String>,
+    /// Check that the PTR name resolves back to the same address
+    pub fcrdns: bool,
+}
+
+/// A PTR answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct PtrAnswer {
+    pub hostname: String,
+    /// FCrDNS result, None when checking is disabled
+    pub fcrdns: Option<bool>,
+}
+
+pub struct ReverseDns {
+    config: DnsConfig,
+    /// Only built when servers are configured; the system resolver is used otherwise
+    resolver: Option<TokioAsyncResolver>,
+}
+
+impl ReverseDns {
+    pub fn new(config: &DnsConfig) -> Self {
+        let resolver = if config.servers.is_empty() {
+            None
+        } else {
+            let servers = NameServerConfigGroup::from_ips_clear(&config.servers, 53, true);
+            let mut opts = ResolverOpts::default();
+            opts.timeout = Duration::from_secs(1);
+            opts.attempts = 1;
+            Some(TokioAsyncResolver::tokio(
+                ResolverConfig::from_parts(None, Vec::new(), servers),
+                opts,
+            ))
+        };
+
+        Self {
+            config: config.clone(),
+            resolver,
+        }
+    }
+
+    pub async fn lookup(&self, ip: IpAddr) -> Option<PtrAnswer> {
+        let name = self.ptr(ip).await?;
+        // Filter out IP addresses returned as hostnames
+        if name.chars().next()?.is_numeric() {
+            return None;
+        }
+
+        let fcrdns = if self.config.fcrdns {
+            Some(self.forward_confirms(&name, ip).await)
+        } else {
+            None
+        };
+
+        Some(PtrAnswer {
+            hostname: strip_domain(&name, self.config.strip_domain.as_deref()),
+            fcrdns,
+        })
+    }
+
+    async fn ptr(&self, ip: IpAddr) -> Option<String> {
+        let name = match &self.resolver {
+            Some(resolver) => resolver.reverse_lookup(ip).await.ok()?.iter().next()?.to_string(),
+            None => tokio::task::spawn_blocking(move || dns_lookup::lookup_addr(&ip))
+                .await
+                .ok()?
+                .ok()?,
+        };
+        Some(name.trim_end_matches('.').to_string())
+    }
+
+    /// True if `name` has an address record pointing back to `ip`
+    async fn forward_confirms(&self, name: &str, ip: IpAddr) -> bool {
+        match &self.resolver {
+            Some(resolver) => match resolver.lookup_ip(format!("{}.", name)).await {
+                Ok(addresses) => addresses.iter().any(|addr| addr == ip),
+                Err(_) => false,
+            },
+            None => {
+                let name = name.to_string();
+                tokio::task::spawn_blocking(move || dns_lookup::lookup_host(&name))
+                    .await
+                    .ok()
+                    .and_then(|result| result.ok())
+                    .is_some_and(|mut addresses| addresses.any(|addr| addr == ip))
+            }
+        }
+    }
+}
+
+/// Remove `suffix` (with or without a leading dot) from the end of `name`, ignoring case
+fn strip_domain(name: &str, suffix: Option<&str>) -> String {
+    let Some(suffix) = suffix.map(|s| s.trim_matches('.')).filter(|s| !s.is_empty()) else {
+        return name.to_string();
+    };
+
+    let cut = name.len().saturating_sub(suffix.len() + 1);
+    match (name.get(..cut), name.get(cut..)) {
+        (Some(host), Some(tail))
+            if !host.is_empty()
+                && tail.starts_with('.')
+                && tail[1..].eq_ignore_ascii_case(suffix) =>
+        {
+            host.to_string()
+        }
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_domain() {
+        assert_eq!(strip_domain("laptop.lan", Some("lan")), "laptop");
+        assert_eq!(strip_domain("laptop.home.arpa", Some(".home.arpa")), "laptop");
+        assert_eq!(strip_domain("laptop.LAN", Some("lan")), "laptop");
+        assert_eq!(strip_domain("laptop.example.com", Some("lan")), "laptop.example.com");
+        assert_eq!(strip_domain("lan", Some("lan")), "lan");
+        assert_eq!(strip_domain("myplan", Some("lan")), "myplan");
+        assert_eq!(strip_domain("laptop.lan", None), "laptop.lan");
+    }
+}
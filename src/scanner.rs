@@ -1,13 +1,26 @@
+use chrono::Utc;
 use pnet::datalink::{self, Channel, MacAddr, NetworkInterface};
 use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, ArpPacket, MutableArpPacket};
 use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
 use pnet::packet::{MutablePacket, Packet};
-use std::net::Ipv4Addr;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
 
-use crate::models::Device;
+use crate::classify;
+use crate::config::Config;
+use crate::dhcp;
+use crate::dns_sd;
+use crate::hostname_cache::{CacheLookup, HostnameCache};
+use crate::mac::MacAddress;
+use crate::reverse_dns::ReverseDns;
+use crate::models::{Device, DeviceStatus, HostnameSource};
+use crate::ports;
+use crate::snmp;
+use crate::ssdp;
 use crate::vendor::VendorDb;
 use crate::utils;
 
@@ -15,8 +28,9 @@ pub async fn scan_network(
     interface_name: Option<String>,
     target_ips: Vec<Ipv4Addr>,
     resolve_hostnames: bool,
+    config: &Config,
 ) -> Vec<Device> {
-    let vendor_db = VendorDb::new();
+    let vendor_db = VendorDb::shared(&config.vendor);
     let interface = if let Some(name) = interface_name {
         datalink::interfaces()
             .into_iter()
@@ -32,7 +46,7 @@ pub async fn scan_network(
         .iter()
         .find(|ip| ip.is_ipv4())
         .map(|ip| ip.ip())
-        .expect(&format!("Network interface '{}' has no IPv4 address assigned", interface.name));
+        .unwrap_or_else(|| panic!("Network interface '{}' has no IPv4 address assigned", interface.name));
     let source_ipv4 = match source_ip {
         std::net::IpAddr::V4(ip) => ip,
         _ => return Vec::new(),
@@ -47,6 +61,14 @@ pub async fn scan_network(
         ),
     };
 
+    // SSDP answers arrive while the ARP sweep runs
+    let ssdp_search = config.ssdp.enabled.then(|| {
+        tokio::spawn(ssdp::discover(Duration::from_millis(config.ssdp.listen_window_ms)))
+    });
+    let service_browse = config.dns_sd.enabled.then(|| {
+        tokio::spawn(dns_sd::browse(Duration::from_millis(config.dns_sd.listen_window_ms)))
+    });
+
     let devices = Arc::new(Mutex::new(Vec::<Device>::new()));
     let devices_clone = devices.clone();
 
@@ -57,43 +79,35 @@ pub async fn scan_network(
             if start.elapsed() > Duration::from_secs(10) {
                 break;
             }
-            match rx.next() {
-                Ok(packet) => {
-                    let packet = EthernetPacket::new(packet).unwrap();
-                    if packet.get_ethertype() == EtherTypes::Arp {
-                        let arp_packet = ArpPacket::new(packet.payload()).unwrap();
-                        if arp_packet.get_operation() == ArpOperations::Reply {
-                            if arp_packet.get_target_proto_addr() == source_ipv4 {
-                                let sender_mac = arp_packet.get_sender_hw_addr();
-                                let sender_ip = arp_packet.get_sender_proto_addr();
-
-                                // Vendor lookup using OUI database
-                                let mac_str = sender_mac.to_string();
-                                let vendor = vendor_db.lookup(&mac_str);
-
-                                // Create device without hostname first (will resolve later)
-                                let device = Device::new(
-                                    mac_str,
-                                    sender_ip.to_string(),
-                                    None,
-                                    vendor,
-                                );
-
-                                let mut devs = devices_clone.lock().unwrap();
-                                if !devs.iter().any(|d| d.mac == device.mac) {
-                                    devs.push(device);
-                                }
-                            }
+            if let Ok(packet) = rx.next() {
+                let packet = EthernetPacket::new(packet).unwrap();
+                if packet.get_ethertype() == EtherTypes::Arp {
+                    let arp_packet = ArpPacket::new(packet.payload()).unwrap();
+                    if arp_packet.get_operation() == ArpOperations::Reply
+                        && arp_packet.get_target_proto_addr() == source_ipv4
+                    {
+                        let sender_mac = arp_packet.get_sender_hw_addr();
+                        let sender_ip = arp_packet.get_sender_proto_addr();
+
+                        // Vendor lookup using OUI database
+                        let mac = MacAddress::from(sender_mac);
+                        let vendor = vendor_db.lookup(mac);
+
+                        // Create device without hostname first (will resolve later)
+                        let device = Device::new(mac, sender_ip, None, vendor);
+
+                        let mut devs = devices_clone.lock().unwrap();
+                        if !devs.iter().any(|d| d.mac == device.mac) {
+                            devs.push(device);
                         }
                     }
                 }
-                Err(_) => {}
             }
         }
     });
 
     // Sender Logic
-    for target_ip in target_ips {
+    for &target_ip in &target_ips {
         send_arp_request(&mut *tx, &interface, source_ipv4, target_ip);
         // Delay between requests to avoid flooding and packet loss
         thread::sleep(Duration::from_millis(2));
@@ -103,32 +117,183 @@ pub async fn scan_network(
 
     let mut result = devices.lock().unwrap().clone();
 
+    if config.snmp.enabled {
+        import_router_arp(&mut result, &target_ips, vendor_db, config).await;
+    }
+
+    // DHCP leases validate MACs and give fast, reliable hostnames
+    let dhcp_leases = dhcp::load_leases(&config.dhcp);
+    for device in &mut result {
+        dhcp::apply_lease(device, &dhcp_leases, vendor_db, false);
+    }
+
+    // Friendly names and models from UPnP descriptions
+    if let Some(search) = ssdp_search
+        && let Ok(mut upnp) = search.await
+    {
+        for device in &mut result {
+            device.upnp = upnp.remove(&device.ip);
+        }
+    }
+
+    // Advertised mDNS services, and the device type they point to
+    if let Some(browse) = service_browse
+        && let Ok(mut services) = browse.await
+    {
+        for device in &mut result {
+            device.services = services.remove(&device.ip).unwrap_or_default();
+        }
+    }
+
+    // SNMP system group from managed switches, APs and printers
+    if config.snmp.enabled {
+        query_snmp(&mut result, config).await;
+    }
+
+    // Open well-known ports, a hint for the device class
+    if config.ports.enabled {
+        probe_ports(&mut result, config).await;
+    }
+
+    for device in &mut result {
+        device.device_type = classify::classify(device);
+    }
+
     // Only resolve hostnames if requested (can be slow)
     if resolve_hostnames {
-        // Load DHCP leases for faster hostname resolution
-        let dhcp_leases = utils::load_dhcp_leases();
+        let now = Utc::now();
+        let mut cache = if config.hostname_cache.enabled {
+            HostnameCache::load()
+        } else {
+            HostnameCache::default()
+        };
+        let reverse_dns = ReverseDns::new(&config.dns);
 
         // Resolve hostnames for all discovered devices (async with timeout)
         for device in &mut result {
-            if let Ok(ip) = device.ip.parse::<std::net::IpAddr>() {
-                // Use 3 second timeout per device to avoid long delays
-                if let Some(hostname) = utils::resolve_hostname_with_timeout(
-                    ip,
-                    Some(&dhcp_leases),
-                    3,
-                )
-                .await
-                {
-                    device.hostname = Some(hostname);
-                }
+            // Fresh cache entries (and manual names) skip the network entirely
+            if config.hostname_cache.enabled
+                && let CacheLookup::Hit(entry) =
+                    cache.lookup(device.mac, &config.hostname_cache, now)
+            {
+                device.hostname = entry.hostname.clone();
+                device.hostname_source = entry.source;
+                device.fcrdns = entry.fcrdns;
+                device.other_hostnames = entry.others.clone();
+                device.netbios = entry.netbios.clone();
+                continue;
             }
+
+            let answers = utils::resolve_hostnames(
+                IpAddr::V4(device.ip),
+                Some(&dhcp_leases),
+                &reverse_dns,
+                Some(device.mac),
+                &config.resolver,
+            )
+            .await;
+            device.set_hostnames(&answers);
+            cache.insert(device.mac, &answers, now);
+        }
+
+        if config.hostname_cache.enabled
+            && let Err(e) = cache.save()
+        {
+            eprintln!("Failed to save hostname cache: {}", e);
         }
     }
 
+    // sysName names managed gear that no other resolver knows
+    for device in &mut result {
+        if device.hostname.is_none()
+            && let Some(sys_name) = device.snmp.as_ref().and_then(|s| s.sys_name.clone())
+        {
+            device.hostname = Some(sys_name);
+            device.hostname_source = Some(HostnameSource::Snmp);
+        }
+    }
+
+    // The class also weighs hostnames, so it comes last
+    for device in &mut result {
+        device.classification = classify::infer_class(device);
+    }
+
     result
 }
 
-fn get_default_interface() -> Option<NetworkInterface> {
+/// Add the hosts from the configured routers' ARP tables that are in the scanned range
+/// but didn't answer our ARP requests (e.g. hosts on other subnets)
+async fn import_router_arp(
+    devices: &mut Vec<Device>,
+    target_ips: &[Ipv4Addr],
+    vendor_db: &VendorDb,
+    config: &Config,
+) {
+    let in_range: HashSet<Ipv4Addr> = target_ips.iter().copied().collect();
+
+    for router in &config.snmp.routers {
+        let entries = snmp::arp_table(router.ip, &router.community, config.snmp.timeout()).await;
+        if entries.is_empty() {
+            eprintln!("Warning: no ARP entries read from router {} over SNMP", router.ip);
+        }
+
+        for (ip, mac) in entries {
+            if !in_range.contains(&ip) {
+                continue;
+            }
+            if devices.iter().any(|d| d.ip == ip || d.mac == mac) {
+                continue;
+            }
+            let vendor = vendor_db.lookup(mac);
+            let mut device = Device::new(mac, ip, None, vendor);
+            // Known to the router, but it didn't answer us
+            device.status = DeviceStatus::Unknown;
+            devices.push(device);
+        }
+    }
+}
+
+/// Read the SNMP system group of every device with a configured community, in parallel
+async fn query_snmp(devices: &mut [Device], config: &Config) {
+    let mut queries = JoinSet::new();
+    for (index, device) in devices.iter().enumerate() {
+        let ip = device.ip;
+        let Some(community) = config.snmp.community_for(ip) else {
+            continue;
+        };
+        let community = community.to_string();
+        let wait = config.snmp.timeout();
+        queries.spawn(async move { (index, snmp::system_info(ip, &community, wait).await) });
+    }
+
+    while let Some(result) = queries.join_next().await {
+        if let Ok((index, Some(info))) = result {
+            devices[index].snmp = Some(info);
+        }
+    }
+}
+
+/// Try the configured TCP ports on every device, in parallel
+/// At most `ports.max_connections` connections are open at once
+async fn probe_ports(devices: &mut [Device], config: &Config) {
+    let limit = config.ports.connection_limit();
+    let mut probes = JoinSet::new();
+    for (index, device) in devices.iter().enumerate() {
+        let ip = device.ip;
+        let list = config.ports.ports.clone();
+        let wait = config.ports.timeout();
+        let limit = limit.clone();
+        probes.spawn(async move { (index, ports::open_ports(ip, &list, wait, limit).await) });
+    }
+
+    while let Some(result) = probes.join_next().await {
+        if let Ok((index, open)) = result {
+            devices[index].open_ports = open;
+        }
+    }
+}
+
+pub fn get_default_interface() -> Option<NetworkInterface> {
     datalink::interfaces()
         .into_iter()
         .find(|iface| {
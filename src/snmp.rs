@@ -0,0 +1,457 @@
+//! SNMPv2c client (RFC 3416) with a minimal BER codec
+//! Reads the system group of managed switches, APs and printers, and walks a router's
+//! ipNetToMediaTable to import ARP entries from subnets the scanner can't reach
+
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout_at, Instant};
+
+use crate::mac::MacAddress;
+use crate::utils;
+
+pub const SNMP_PORT: u16 = 161;
+
+const SYS_DESCR: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 1, 0];
+const SYS_OBJECT_ID: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 2, 0];
+const SYS_NAME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 5, 0];
+const SYS_LOCATION: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 6, 0];
+/// ipNetToMediaPhysAddress, indexed by ifIndex.a.b.c.d
+const IP_NET_TO_MEDIA_PHYS_ADDRESS: &[u32] = &[1, 3, 6, 1, 2, 1, 4, 22, 1, 2];
+
+/// Upper bound on GETNEXT requests for a single table walk
+const MAX_WALK_STEPS: usize = 4096;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const PDU_GET: u8 = 0xA0;
+const PDU_GET_NEXT: u8 = 0xA1;
+const PDU_RESPONSE: u8 = 0xA2;
+/// noSuchObject, noSuchInstance and endOfMibView exceptions
+const EXCEPTION_TAGS: [u8; 3] = [0x80, 0x81, 0x82];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnmpConfig {
+    pub enabled: bool,
+    /// Communities to use per IP range; the first matching entry wins
+    pub communities: Vec<SnmpCommunity>,
+    /// Routers whose ARP table (ipNetToMediaTable) is imported on each scan
+    pub routers: Vec<SnmpRouter>,
+    /// How long to wait for each answer, in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for SnmpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            communities: Vec::new(),
+            routers: Vec::new(),
+            timeout_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnmpCommunity {
+    /// Range in scan format (e.g. "192.168.1.1-254"); matches every IP if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<String>,
+    pub community: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnmpRouter {
+    pub ip: Ipv4Addr,
+    pub community: String,
+}
+
+impl SnmpConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// Community configured for `ip`, None if no range covers it
+    pub fn community_for(&self, ip: Ipv4Addr) -> Option<&str> {
+        self.communities
+            .iter()
+            .find(|entry| match &entry.range {
+                Some(range) => utils::parse_ip_range(range).is_ok_and(|ips| ips.contains(&ip)),
+                None => true,
+            })
+            .map(|entry| entry.community.as_str())
+    }
+}
+
+/// The SNMP system group of a device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnmpInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sys_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sys_descr: Option<String>,
+    /// Dotted vendor OID identifying the product (e.g. "1.3.6.1.4.1.9.1.1208")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sys_object_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sys_location: Option<String>,
+}
+
+/// A variable binding: OID and value
+type VarBind = (Vec<u32>, Value);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bytes(Vec<u8>),
+    Oid(Vec<u32>),
+    /// NULL or one of the "no such object" exceptions
+    Missing,
+    Other,
+}
+
+/// Read sysName, sysDescr, sysObjectID and sysLocation with a single GET
+pub async fn system_info(ip: Ipv4Addr, community: &str, wait: Duration) -> Option<SnmpInfo> {
+    let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .ok()?;
+    socket.connect(SocketAddrV4::new(ip, SNMP_PORT)).await.ok()?;
+
+    let oids = [SYS_NAME, SYS_DESCR, SYS_OBJECT_ID, SYS_LOCATION];
+    let varbinds = request(&socket, community, PDU_GET, &oids, wait).await?;
+    let value = |oid: &[u32]| {
+        varbinds
+            .iter()
+            .find(|(name, _)| name.as_slice() == oid)
+            .map(|(_, value)| value)
+    };
+
+    let info = SnmpInfo {
+        sys_name: value(SYS_NAME).and_then(text),
+        sys_descr: value(SYS_DESCR).and_then(text),
+        sys_object_id: match value(SYS_OBJECT_ID) {
+            Some(Value::Oid(oid)) => Some(dotted(oid)),
+            _ => None,
+        },
+        sys_location: value(SYS_LOCATION).and_then(text),
+    };
+    if info.sys_name.is_none() && info.sys_descr.is_none() && info.sys_object_id.is_none() {
+        return None;
+    }
+    Some(info)
+}
+
+/// Walk a router's ipNetToMediaTable, returning its (IP, MAC) entries
+/// MACs are lowercase and colon separated, like the ARP scanner output
+pub async fn arp_table(router: Ipv4Addr, community: &str, wait: Duration) -> Vec<(Ipv4Addr, MacAddress)> {
+    let mut entries = Vec::new();
+    let Ok(socket) = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)).await else {
+        return entries;
+    };
+    if socket.connect(SocketAddrV4::new(router, SNMP_PORT)).await.is_err() {
+        return entries;
+    }
+
+    let mut cursor = IP_NET_TO_MEDIA_PHYS_ADDRESS.to_vec();
+    for _ in 0..MAX_WALK_STEPS {
+        let Some(varbinds) = request(&socket, community, PDU_GET_NEXT, &[&cursor], wait).await else {
+            break;
+        };
+        let Some((oid, value)) = varbinds.into_iter().next() else {
+            break;
+        };
+        // Left the table (or hit endOfMibView), or the agent stopped making progress
+        if !oid.starts_with(IP_NET_TO_MEDIA_PHYS_ADDRESS) || oid <= cursor {
+            break;
+        }
+
+        // Index: ifIndex followed by the four octets of the IP
+        if let (Some(ip), Value::Bytes(mac)) = (index_ip(&oid), &value)
+            && mac.iter().any(|&b| b != 0)
+            && let Ok(mac) = <[u8; 6]>::try_from(mac.as_slice())
+        {
+            entries.push((ip, MacAddress::new(mac)));
+        }
+        cursor = oid;
+    }
+
+    entries
+}
+
+/// Send one request and wait for the matching response's variable bindings
+async fn request(
+    socket: &UdpSocket,
+    community: &str,
+    pdu_type: u8,
+    oids: &[&[u32]],
+    wait: Duration,
+) -> Option<Vec<VarBind>> {
+    let request_id = rand_request_id();
+    let packet = build_request(community, pdu_type, request_id, oids);
+    socket.send(&packet).await.ok()?;
+
+    let deadline = Instant::now() + wait;
+    let mut buf = [0u8; 4096];
+    loop {
+        let len = match timeout_at(deadline, socket.recv(&mut buf)).await {
+            Ok(Ok(len)) => len,
+            // Timed out or socket error (e.g. ICMP port unreachable)
+            _ => return None,
+        };
+        if let Some((id, varbinds)) = parse_response(&buf[..len])
+            && id == request_id
+        {
+            return Some(varbinds);
+        }
+    }
+}
+
+/// Request ids only need to tell our own outstanding requests apart
+fn rand_request_id() -> i32 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    (nanos & 0x7FFF_FFFF) as i32
+}
+
+fn build_request(community: &str, pdu_type: u8, request_id: i32, oids: &[&[u32]]) -> Vec<u8> {
+    let mut varbinds = Vec::new();
+    for oid in oids {
+        let mut varbind = encode_tlv(TAG_OID, &encode_oid(oid));
+        varbind.extend(encode_tlv(TAG_NULL, &[]));
+        varbinds.extend(encode_tlv(TAG_SEQUENCE, &varbind));
+    }
+
+    let mut pdu = encode_tlv(TAG_INTEGER, &encode_int(request_id as i64));
+    pdu.extend(encode_tlv(TAG_INTEGER, &[0])); // error-status
+    pdu.extend(encode_tlv(TAG_INTEGER, &[0])); // error-index
+    pdu.extend(encode_tlv(TAG_SEQUENCE, &varbinds));
+
+    let mut message = encode_tlv(TAG_INTEGER, &[1]); // version: v2c
+    message.extend(encode_tlv(TAG_OCTET_STRING, community.as_bytes()));
+    message.extend(encode_tlv(pdu_type, &pdu));
+    encode_tlv(TAG_SEQUENCE, &message)
+}
+
+/// Returns the request id and variable bindings of a Response PDU without errors
+fn parse_response(buf: &[u8]) -> Option<(i32, Vec<VarBind>)> {
+    let (tag, message, _) = read_tlv(buf)?;
+    if tag != TAG_SEQUENCE {
+        return None;
+    }
+    let (_, _version, rest) = read_tlv(message)?;
+    let (_, _community, rest) = read_tlv(rest)?;
+    let (tag, pdu, _) = read_tlv(rest)?;
+    if tag != PDU_RESPONSE {
+        return None;
+    }
+
+    let (_, id, rest) = read_tlv(pdu)?;
+    let (_, error_status, rest) = read_tlv(rest)?;
+    let (_, _error_index, rest) = read_tlv(rest)?;
+    if decode_int(error_status)? != 0 {
+        return None;
+    }
+
+    let (_, mut list, _) = read_tlv(rest)?;
+    let mut varbinds = Vec::new();
+    while !list.is_empty() {
+        let (_, varbind, next) = read_tlv(list)?;
+        let (_, oid, value_tlv) = read_tlv(varbind)?;
+        let (value_tag, value, _) = read_tlv(value_tlv)?;
+        let value = match value_tag {
+            TAG_OCTET_STRING => Value::Bytes(value.to_vec()),
+            TAG_OID => Value::Oid(decode_oid(value)?),
+            TAG_NULL => Value::Missing,
+            t if EXCEPTION_TAGS.contains(&t) => Value::Missing,
+            _ => Value::Other,
+        };
+        varbinds.push((decode_oid(oid)?, value));
+        list = next;
+    }
+
+    Some((decode_int(id)? as i32, varbinds))
+}
+
+fn encode_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else if len <= 0xFF {
+        out.extend([0x81, len as u8]);
+    } else {
+        out.extend([0x82, (len >> 8) as u8, len as u8]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// Reads one TLV, returning its tag, contents and the bytes after it
+fn read_tlv(buf: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *buf.first()?;
+    let first = *buf.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7F;
+        if count == 0 || count > 2 {
+            return None;
+        }
+        let bytes = buf.get(2..2 + count)?;
+        (bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize), 2 + count)
+    };
+    let content = buf.get(header..header + len)?;
+    Some((tag, content, &buf[header + len..]))
+}
+
+/// Minimal two's complement encoding
+fn encode_int(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut start = 0;
+    while start < 7
+        && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xFF && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    bytes[start..].to_vec()
+}
+
+fn decode_int(content: &[u8]) -> Option<i64> {
+    if content.is_empty() || content.len() > 8 {
+        return None;
+    }
+    let sign = if content[0] & 0x80 != 0 { -1i64 } else { 0 };
+    Some(content.iter().fold(sign, |acc, &b| (acc << 8) | b as i64))
+}
+
+fn encode_oid(oid: &[u32]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if oid.len() < 2 {
+        return out;
+    }
+    let arcs = std::iter::once(oid[0] * 40 + oid[1]).chain(oid[2..].iter().copied());
+    for arc in arcs {
+        let mut chunk = vec![(arc & 0x7F) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            chunk.push((rest & 0x7F) as u8 | 0x80);
+            rest >>= 7;
+        }
+        out.extend(chunk.iter().rev());
+    }
+    out
+}
+
+fn decode_oid(content: &[u8]) -> Option<Vec<u32>> {
+    let mut arcs = Vec::new();
+    let mut value: u32 = 0;
+    for &b in content {
+        value = value.checked_mul(128)? | (b & 0x7F) as u32;
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    Some(arcs)
+}
+
+fn dotted(oid: &[u32]) -> String {
+    oid.iter().map(|arc| arc.to_string()).collect::<Vec<_>>().join(".")
+}
+
+/// Printable text of an OCTET STRING, None if empty
+fn text(value: &Value) -> Option<String> {
+    let Value::Bytes(bytes) = value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(bytes)
+        .trim_matches(|c: char| c.is_whitespace() || c == '\0')
+        .to_string();
+    if text.is_empty() { None } else { Some(text) }
+}
+
+/// IP address at the end of an ipNetToMediaTable OID
+fn index_ip(oid: &[u32]) -> Option<Ipv4Addr> {
+    let index = oid.get(IP_NET_TO_MEDIA_PHYS_ADDRESS.len() + 1..)?;
+    match index {
+        [a, b, c, d] => Some(Ipv4Addr::new(
+            u8::try_from(*a).ok()?,
+            u8::try_from(*b).ok()?,
+            u8::try_from(*c).ok()?,
+            u8::try_from(*d).ok()?,
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oid_and_int_round_trip() {
+        let oid = [1, 3, 6, 1, 4, 1, 9, 1, 1208, 300000];
+        assert_eq!(decode_oid(&encode_oid(&oid)).unwrap(), oid);
+        assert_eq!(encode_oid(SYS_NAME), vec![0x2B, 6, 1, 2, 1, 1, 5, 0]);
+        for value in [0, 1, 127, 128, 255, 256, -1, -129, i32::MAX as i64] {
+            assert_eq!(decode_int(&encode_int(value)), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_parse_response() {
+        // sysName.0 = "core-sw1" and one ipNetToMediaTable entry, as an agent would answer
+        let mut varbinds = Vec::new();
+        let mut varbind = encode_tlv(TAG_OID, &encode_oid(SYS_NAME));
+        varbind.extend(encode_tlv(TAG_OCTET_STRING, b"core-sw1"));
+        varbinds.extend(encode_tlv(TAG_SEQUENCE, &varbind));
+        let entry: Vec<u32> = IP_NET_TO_MEDIA_PHYS_ADDRESS
+            .iter()
+            .copied()
+            .chain([3, 10, 0, 5, 20])
+            .collect();
+        let mut varbind = encode_tlv(TAG_OID, &encode_oid(&entry));
+        varbind.extend(encode_tlv(TAG_OCTET_STRING, &[0xbc, 0x24, 0x11, 0, 0, 1]));
+        varbinds.extend(encode_tlv(TAG_SEQUENCE, &varbind));
+
+        let mut pdu = encode_tlv(TAG_INTEGER, &encode_int(4242));
+        pdu.extend(encode_tlv(TAG_INTEGER, &[0]));
+        pdu.extend(encode_tlv(TAG_INTEGER, &[0]));
+        pdu.extend(encode_tlv(TAG_SEQUENCE, &varbinds));
+        let mut message = encode_tlv(TAG_INTEGER, &[1]);
+        message.extend(encode_tlv(TAG_OCTET_STRING, b"public"));
+        message.extend(encode_tlv(PDU_RESPONSE, &pdu));
+        let packet = encode_tlv(TAG_SEQUENCE, &message);
+
+        let (id, varbinds) = parse_response(&packet).unwrap();
+        assert_eq!(id, 4242);
+        assert_eq!(text(&varbinds[0].1).as_deref(), Some("core-sw1"));
+        assert_eq!(index_ip(&varbinds[1].0), Some(Ipv4Addr::new(10, 0, 5, 20)));
+    }
+
+    #[test]
+    fn test_community_for_range() {
+        let config: SnmpConfig = serde_json::from_str(
+            r#"{ "communities": [
+                { "range": "10.0.0.1-20", "community": "switches" },
+                { "community": "public" }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(config.community_for(Ipv4Addr::new(10, 0, 0, 5)), Some("switches"));
+        assert_eq!(config.community_for(Ipv4Addr::new(10, 0, 0, 50)), Some("public"));
+    }
+}
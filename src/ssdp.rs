@@ -0,0 +1,312 @@
+//! SSDP/UPnP discovery
+//! TVs, printers, NAS boxes and routers answer M-SEARCH requests on 239.255.255.250:1900
+//! and announce themselves with NOTIFY messages. Both carry a LOCATION URL pointing to a
+//! device description XML with the friendly name and model details
+
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task::JoinSet;
+use tokio::time::{timeout_at, Instant};
+
+pub const SSDP_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+pub const SSDP_PORT: u16 = 1900;
+
+/// How long to wait for description XML downloads
+const DESCRIPTION_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SsdpConfig {
+    pub enabled: bool,
+    /// How long each scan listens for M-SEARCH answers, in milliseconds
+    pub listen_window_ms: u64,
+}
+
+impl Default for SsdpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            listen_window_ms: 2000,
+        }
+    }
+}
+
+/// Details from a UPnP device description
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpnpInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub friendly_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_number: Option<String>,
+    /// UPnP device type URN, e.g. "urn:schemas-upnp-org:device:MediaRenderer:1"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_type: Option<String>,
+    /// Description URL the details were read from
+    pub location: String,
+}
+
+impl UpnpInfo {
+    /// Short text for tables, e.g. "Living Room TV (QN55Q60)"
+    pub fn summary(&self) -> String {
+        let model = match (&self.model_name, &self.model_number) {
+            (Some(name), Some(number)) if !name.contains(number.as_str()) => {
+                Some(format!("{} {}", name, number))
+            }
+            (Some(name), _) => Some(name.clone()),
+            (None, number) => number.clone(),
+        };
+        match (&self.friendly_name, model) {
+            (Some(friendly), Some(model)) if friendly != &model => format!("{} ({})", friendly, model),
+            (Some(friendly), _) => friendly.clone(),
+            (None, Some(model)) => model,
+            (None, None) => self.manufacturer.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Send an M-SEARCH for all devices and collect the descriptions of everything that answers
+/// Keyed by the IP that answered; the first LOCATION per IP wins
+pub async fn discover(listen_window: Duration) -> HashMap<Ipv4Addr, UpnpInfo> {
+    let locations = match search(listen_window).await {
+        Ok(locations) => locations,
+        Err(e) => {
+            eprintln!("Warning: SSDP search failed: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    let client = http_client();
+    let mut downloads = JoinSet::new();
+    for (ip, location) in locations {
+        let client = client.clone();
+        downloads.spawn(async move { (ip, fetch_description(&client, ip, &location).await) });
+    }
+
+    let mut found = HashMap::new();
+    while let Some(result) = downloads.join_next().await {
+        if let Ok((ip, Some(info))) = result {
+            found.insert(ip, info);
+        }
+    }
+    found
+}
+
+async fn search(listen_window: Duration) -> std::io::Result<HashMap<Ipv4Addr, String>> {
+    let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)).await?;
+    // UPnP recommends a TTL of 2 for SSDP multicast
+    socket.set_multicast_ttl_v4(2)?;
+
+    let mx = listen_window.as_secs().clamp(1, 5);
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}:{}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: ssdp:all\r\n\r\n",
+        SSDP_ADDR, SSDP_PORT, mx
+    );
+    socket
+        .send_to(request.as_bytes(), SocketAddrV4::new(SSDP_ADDR, SSDP_PORT))
+        .await?;
+
+    let deadline = Instant::now() + listen_window;
+    let mut buf = [0u8; 2048];
+    let mut locations = HashMap::new();
+
+    while let Ok(received) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, SocketAddr::V4(from)) = received? else {
+            continue;
+        };
+        let headers = parse_headers(&String::from_utf8_lossy(&buf[..len]));
+        if let Some(location) = headers.get("location") {
+            locations
+                .entry(*from.ip())
+                .or_insert_with(|| location.clone());
+        }
+    }
+
+    Ok(locations)
+}
+
+/// Listen for NOTIFY announcements on the SSDP group and send the description of every
+/// device that comes alive (or moves its description URL)
+/// Returns None if port 1900 can't be joined
+pub fn listen_notify() -> Option<UnboundedReceiver<(Ipv4Addr, UpnpInfo)>> {
+    let socket = match notify_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Warning: cannot listen for SSDP announcements: {}", e);
+            return None;
+        }
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let client = http_client();
+        // Devices repeat their announcements every few minutes, only new locations are fetched
+        let mut known: HashMap<Ipv4Addr, String> = HashMap::new();
+        let mut buf = [0u8; 2048];
+
+        loop {
+            let (len, from) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    eprintln!("Warning: SSDP listener stopped: {}", e);
+                    return;
+                }
+            };
+            let SocketAddr::V4(from) = from else {
+                continue;
+            };
+
+            let message = String::from_utf8_lossy(&buf[..len]);
+            if !message.starts_with("NOTIFY") {
+                continue;
+            }
+            let headers = parse_headers(&message);
+            if headers.get("nts").map(String::as_str) != Some("ssdp:alive") {
+                continue;
+            }
+            let Some(location) = headers.get("location") else {
+                continue;
+            };
+
+            let ip = *from.ip();
+            if known.get(&ip) == Some(location) {
+                continue;
+            }
+            known.insert(ip, location.clone());
+
+            if let Some(info) = fetch_description(&client, ip, location).await
+                && tx.send((ip, info)).is_err()
+            {
+                // Receiver dropped: the monitor is gone
+                return;
+            }
+        }
+    });
+
+    Some(rx)
+}
+
+/// Bind the shared SSDP port; other UPnP software on this host usually holds it too
+fn notify_socket() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, SSDP_PORT).into())?;
+    socket.join_multicast_v4(&SSDP_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+fn http_client() -> Client {
+    Client::builder()
+        .timeout(DESCRIPTION_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Download and parse a device description
+/// Only URLs on the announcing host are fetched, so a spoofed packet can't point us elsewhere
+async fn fetch_description(client: &Client, ip: Ipv4Addr, location: &str) -> Option<UpnpInfo> {
+    let url = Url::parse(location).ok()?;
+    if url.scheme() != "http" || url.host_str()?.parse::<IpAddr>().ok()? != IpAddr::V4(ip) {
+        return None;
+    }
+
+    let xml = client.get(url).send().await.ok()?.text().await.ok()?;
+    parse_description(&xml, location)
+}
+
+/// HTTP-style headers of an SSDP message, names lowercased
+fn parse_headers(message: &str) -> HashMap<String, String> {
+    message
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect()
+}
+
+/// Read the root device's details from a description XML
+fn parse_description(xml: &str, location: &str) -> Option<UpnpInfo> {
+    // Embedded devices repeat the same tags, the root device comes first
+    let device = &xml[xml.find("<device")?..];
+    let info = UpnpInfo {
+        friendly_name: tag_text(device, "friendlyName"),
+        manufacturer: tag_text(device, "manufacturer"),
+        model_name: tag_text(device, "modelName"),
+        model_number: tag_text(device, "modelNumber"),
+        device_type: tag_text(device, "deviceType"),
+        location: location.to_string(),
+    };
+
+    if info.friendly_name.is_none() && info.manufacturer.is_none() && info.model_name.is_none() {
+        return None;
+    }
+    Some(info)
+}
+
+/// Text of the first `<tag>...</tag>`, with the XML entities decoded
+fn tag_text(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+
+    let text = xml[start..end]
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    if text.is_empty() { None } else { Some(text) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notify_headers() {
+        let message = "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n\
+            Location: http://192.168.1.20:8060/dial/dd.xml\r\nNTS: ssdp:alive\r\n\r\n";
+        let headers = parse_headers(message);
+        assert_eq!(headers.get("location").unwrap(), "http://192.168.1.20:8060/dial/dd.xml");
+        assert_eq!(headers.get("nts").unwrap(), "ssdp:alive");
+    }
+
+    #[test]
+    fn test_parse_description() {
+        let xml = r#"<?xml version="1.0"?>
+            <root xmlns="urn:schemas-upnp-org:device-1-0">
+              <specVersion><major>1</major><minor>0</minor></specVersion>
+              <device>
+                <deviceType>urn:schemas-upnp-org:device:MediaRenderer:1</deviceType>
+                <friendlyName>Living Room TV</friendlyName>
+                <manufacturer>Samsung Electronics</manufacturer>
+                <modelName>QN55Q60</modelName>
+                <modelNumber>AllShare1.0</modelNumber>
+                <deviceList>
+                  <device><friendlyName>Embedded &amp; Hidden</friendlyName></device>
+                </deviceList>
+              </device>
+            </root>"#;
+        let info = parse_description(xml, "http://192.168.1.30:7676/dmr").unwrap();
+        assert_eq!(info.friendly_name.as_deref(), Some("Living Room TV"));
+        assert_eq!(info.manufacturer.as_deref(), Some("Samsung Electronics"));
+        assert_eq!(info.model_name.as_deref(), Some("QN55Q60"));
+        assert_eq!(info.device_type.as_deref(), Some("urn:schemas-upnp-org:device:MediaRenderer:1"));
+        assert_eq!(info.summary(), "Living Room TV (QN55Q60 AllShare1.0)");
+
+        assert!(parse_description("<root></root>", "http://192.168.1.30/").is_none());
+    }
+}
@@ -0,0 +1,363 @@
+//! Device state transitions between scans: flapping, IP conflicts, expected-offline
+//! schedules and quarantine, and the notification each change raises
+
+use chrono::{DateTime, Datelike, Local, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+use std::net::Ipv4Addr;
+
+use crate::mac::MacAddress;
+use crate::models::{Device, DeviceStatus, Transition};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateConfig {
+    /// Online/offline changes within the window that make a device flapping (0 disables)
+    pub flap_threshold: usize,
+    /// Flapping window, in seconds
+    pub flap_window_secs: i64,
+    /// Times devices are expected to be offline
+    pub offline_schedules: Vec<OfflineSchedule>,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            flap_threshold: 4,
+            flap_window_secs: 1800,
+            offline_schedules: Vec::new(),
+        }
+    }
+}
+
+/// Window (local time) in which devices may be offline without a notification,
+/// e.g. work laptops at night
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineSchedule {
+    /// Devices with one of these tags; every device if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// "HH:MM"; the window may span midnight ("19:00" to "07:30")
+    #[serde(serialize_with = "write_time", deserialize_with = "read_time")]
+    pub from: NaiveTime,
+    #[serde(serialize_with = "write_time", deserialize_with = "read_time")]
+    pub to: NaiveTime,
+    /// Days the window starts on ("mon", "sat", ...); every day if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+}
+
+impl OfflineSchedule {
+    /// Whether `at` falls in the window
+    fn covers(&self, at: DateTime<Local>) -> bool {
+        let time = at.time();
+        // The part after midnight belongs to the window that started the day before
+        let (inside, start_day) = if self.from <= self.to {
+            (self.from <= time && time < self.to, at.weekday())
+        } else if time >= self.from {
+            (true, at.weekday())
+        } else {
+            (time < self.to, at.weekday().pred())
+        };
+        inside && (self.days.is_empty() || self.days.contains(&start_day))
+    }
+}
+
+impl StateConfig {
+    /// Whether `device` is in one of its offline schedules at `at`
+    pub fn expected_offline(&self, device: &Device, at: DateTime<Local>) -> bool {
+        self.offline_schedules
+            .iter()
+            .any(|schedule| device.matches_tags(&schedule.tags) && schedule.covers(at))
+    }
+}
+
+fn read_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&text, "%H:%M")
+        .map_err(|e| serde::de::Error::custom(format!("invalid time '{}': {}", text, e)))
+}
+
+fn write_time<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&time.format("%H:%M"))
+}
+
+/// What the latest scan says about a device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sighting {
+    /// Found by the scan: `confirmed` is false when only a router's ARP table lists it
+    Seen { confirmed: bool, conflict: bool },
+    /// Not found this time, but seen recently enough to still count as online
+    Missed,
+    /// Not seen within the offline threshold
+    Gone,
+}
+
+/// IPs that more than one device answered for, remembered from one scan to the next
+#[derive(Debug, Default)]
+pub struct ConflictTracker {
+    suspects: HashSet<Ipv4Addr>,
+}
+
+impl ConflictTracker {
+    /// Record the answers of a scan, as (IP, MAC, ID of the known device it belongs to), and
+    /// return the IPs in conflict: answered by more than one device in this scan and the one
+    /// before. MACs of the same known device (an alias, or a laptop's wired and Wi-Fi NICs)
+    /// count as one, and a single scan can be a device moving between MACs or a stale entry
+    pub fn update<'a>(
+        &mut self,
+        answers: impl IntoIterator<Item = (Ipv4Addr, MacAddress, Option<&'a str>)>,
+    ) -> HashSet<Ipv4Addr> {
+        let mut owners: HashMap<Ipv4Addr, HashSet<String>> = HashMap::new();
+        for (ip, mac, known_id) in answers {
+            let owner = known_id.map_or_else(|| Device::id_from_mac(mac), str::to_string);
+            owners.entry(ip).or_default().insert(owner);
+        }
+        let duplicates: HashSet<Ipv4Addr> =
+            owners.into_iter().filter(|(_, owners)| owners.len() > 1).map(|(ip, _)| ip).collect();
+
+        let conflicts = duplicates.intersection(&self.suspects).copied().collect();
+        self.suspects = duplicates;
+        conflicts
+    }
+}
+
+/// Status of a device seen for the first time: unknown until a later scan confirms it
+pub fn first_status(blocked: bool, conflict: bool) -> DeviceStatus {
+    if blocked {
+        DeviceStatus::Block
+    } else if conflict {
+        DeviceStatus::Conflict
+    } else {
+        DeviceStatus::Unknown
+    }
+}
+
+/// Move `device` to the status `sighting` calls for
+/// Returns the notification (summary, body) for the change, if it deserves one
+pub fn transition(
+    device: &mut Device,
+    sighting: Sighting,
+    blocked: bool,
+    now: DateTime<Utc>,
+    config: &StateConfig,
+) -> Option<(&'static str, String)> {
+    let previous = device.status;
+    let was_online = match previous {
+        // Flapping always starts at a change, so the latest one tells where the device is
+        DeviceStatus::Flapping => device.transitions.last().is_none_or(|t| t.online),
+        status => status.is_online(),
+    };
+
+    let target = if blocked {
+        DeviceStatus::Block
+    } else {
+        match sighting {
+            Sighting::Seen { conflict: true, .. } => DeviceStatus::Conflict,
+            Sighting::Seen { .. } if device.user.quarantined => DeviceStatus::Quarantined,
+            Sighting::Seen { confirmed: false, .. } if previous == DeviceStatus::Unknown => DeviceStatus::Unknown,
+            Sighting::Seen { .. } => DeviceStatus::Online,
+            // Only a flapping device is re-evaluated while it is still around
+            Sighting::Missed if previous == DeviceStatus::Flapping && device.user.quarantined => {
+                DeviceStatus::Quarantined
+            }
+            Sighting::Missed if previous == DeviceStatus::Flapping => DeviceStatus::Online,
+            Sighting::Missed => previous,
+            // Never confirmed, so never reported as gone either
+            Sighting::Gone if previous == DeviceStatus::Unknown => DeviceStatus::Unknown,
+            Sighting::Gone if config.expected_offline(device, now.with_timezone(&Local)) => {
+                DeviceStatus::ExpectedOffline
+            }
+            Sighting::Gone => DeviceStatus::Offline,
+        }
+    };
+
+    // The blacklist wins over everything, flapping included
+    if target == DeviceStatus::Block {
+        device.status = target;
+        return (previous == DeviceStatus::Offline && matches!(sighting, Sighting::Seen { .. }))
+            .then(|| ("Device Online", format!("{} is back online", device.ip)));
+    }
+
+    if target.is_online() != was_online {
+        device.transitions.push(Transition { at: now, online: target.is_online() });
+    }
+    // Forget changes outside the window, but keep the latest
+    let window_start = now - chrono::Duration::seconds(config.flap_window_secs);
+    let outdated = device.transitions.iter().take_while(|t| t.at < window_start).count();
+    device.transitions.drain(..outdated.min(device.transitions.len().saturating_sub(1)));
+    let recent = device.transitions.iter().filter(|t| t.at >= window_start).count();
+
+    device.status = if config.flap_threshold > 0 && recent >= config.flap_threshold {
+        DeviceStatus::Flapping
+    } else {
+        target
+    };
+    if device.status == previous {
+        return None;
+    }
+
+    let name = device.display_name();
+    match (previous, device.status) {
+        (_, DeviceStatus::Flapping) => Some((
+            "Device Flapping",
+            format!(
+                "{} ({}) went online and offline {} times in {} minutes",
+                name,
+                device.ip,
+                recent,
+                config.flap_window_secs / 60
+            ),
+        )),
+        (DeviceStatus::Flapping, status) => Some(("Device Stable", format!("{} is {} again", name, status))),
+        (_, DeviceStatus::Conflict) => Some((
+            "IP Conflict",
+            format!("{} is used by more than one device, including {} ({})", device.ip, name, device.mac),
+        )),
+        (DeviceStatus::Conflict, DeviceStatus::Online) => {
+            Some(("IP Conflict Resolved", format!("{} only answers from {}", device.ip, name)))
+        }
+        (_, DeviceStatus::Quarantined) => Some((
+            "Quarantined Device Online",
+            format!("{} ({}, {}) is on the network", name, device.ip, device.mac),
+        )),
+        (DeviceStatus::Offline, DeviceStatus::Online) => {
+            Some(("Device Online", format!("{} is back online", device.ip)))
+        }
+        (DeviceStatus::ExpectedOffline, DeviceStatus::Offline) => Some((
+            "Device Offline",
+            format!("{} ({}) is still offline after its scheduled time", name, device.ip),
+        )),
+        (_, DeviceStatus::Offline) => Some(("Device Offline", format!("{} went offline", device.ip))),
+        // Coming back from a schedule, being confirmed, leaving quarantine, or going
+        // offline as scheduled are expected
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn device() -> Device {
+        Device::new("3c:22:fb:00:00:01".parse().unwrap(), Ipv4Addr::new(192, 168, 1, 20), None, None)
+    }
+
+    const SEEN: Sighting = Sighting::Seen { confirmed: true, conflict: false };
+
+    #[test]
+    fn test_flapping() {
+        let config = StateConfig::default();
+        let mut device = device();
+        let start = Utc::now();
+
+        let mut notices = Vec::new();
+        for minute in 0..6 {
+            let sighting = if minute % 2 == 0 { Sighting::Gone } else { SEEN };
+            let at = start + Duration::minutes(minute);
+            notices.push(transition(&mut device, sighting, false, at, &config).map(|(summary, _)| summary));
+        }
+        assert_eq!(
+            notices,
+            vec![Some("Device Offline"), Some("Device Online"), Some("Device Offline"), Some("Device Flapping"), None, None]
+        );
+        assert_eq!(device.status, DeviceStatus::Flapping);
+
+        // Quiet for a whole window: back to normal
+        let later = start + Duration::minutes(60);
+        let notice = transition(&mut device, Sighting::Missed, false, later, &config);
+        assert_eq!(notice.map(|(summary, _)| summary), Some("Device Stable"));
+        assert_eq!(device.status, DeviceStatus::Online);
+        assert_eq!(device.transitions.len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_conflict_and_quarantine() {
+        let config = StateConfig::default();
+        let now = Utc::now();
+        let mut device = device();
+        device.status = DeviceStatus::Unknown;
+
+        // Router ARP entries alone don't confirm a device, and it never goes offline
+        let unconfirmed = Sighting::Seen { confirmed: false, conflict: false };
+        assert!(transition(&mut device, unconfirmed, false, now, &config).is_none());
+        assert!(transition(&mut device, Sighting::Gone, false, now, &config).is_none());
+        assert_eq!(device.status, DeviceStatus::Unknown);
+        assert!(transition(&mut device, SEEN, false, now, &config).is_none());
+        assert_eq!(device.status, DeviceStatus::Online);
+
+        let conflict = Sighting::Seen { confirmed: true, conflict: true };
+        let notice = transition(&mut device, conflict, false, now, &config);
+        assert_eq!(notice.map(|(summary, _)| summary), Some("IP Conflict"));
+        let notice = transition(&mut device, SEEN, false, now, &config);
+        assert_eq!(notice.map(|(summary, _)| summary), Some("IP Conflict Resolved"));
+
+        device.user.quarantined = true;
+        let notice = transition(&mut device, SEEN, false, now, &config);
+        assert_eq!(notice.map(|(summary, _)| summary), Some("Quarantined Device Online"));
+        assert!(transition(&mut device, SEEN, true, now, &config).is_none());
+        assert_eq!(device.status, DeviceStatus::Block);
+    }
+
+    #[test]
+    fn test_offline_schedule() {
+        let config: StateConfig = serde_json::from_str(
+            r#"{ "offline_schedules": [{ "tags": ["work"], "from": "19:00", "to": "07:30", "days": ["mon", "tue"] }] }"#,
+        )
+        .unwrap();
+        let schedule = &config.offline_schedules[0];
+        // 2025-01-06 is a Monday
+        let at = |day, hour, minute| Local.with_ymd_and_hms(2025, 1, day, hour, minute, 0).unwrap();
+        assert!(schedule.covers(at(6, 22, 0)));
+        assert!(schedule.covers(at(7, 6, 0)));
+        assert!(!schedule.covers(at(7, 8, 0)));
+        assert!(!schedule.covers(at(5, 22, 0)));
+        // Tuesday night's window ends on Wednesday morning
+        assert!(schedule.covers(at(8, 7, 0)));
+        assert!(!schedule.covers(at(9, 7, 0)));
+
+        let mut device = device();
+        assert!(!config.expected_offline(&device, at(6, 22, 0)));
+        device.user.tags.push("work".to_string());
+        assert!(config.expected_offline(&device, at(6, 22, 0)));
+
+        assert!(serde_json::from_str::<StateConfig>(r#"{ "offline_schedules": [{ "from": "7pm", "to": "07:00" }] }"#).is_err());
+    }
+
+    #[test]
+    fn test_conflicts_need_two_scans() {
+        let ip = Ipv4Addr::new(192, 168, 1, 20);
+        let first: MacAddress = "3c:22:fb:00:00:01".parse().unwrap();
+        let second: MacAddress = "3c:22:fb:00:00:02".parse().unwrap();
+        let mut tracker = ConflictTracker::default();
+
+        let rivals = [(ip, first, Some("laptop")), (ip, second, None)];
+        assert!(tracker.update(rivals).is_empty());
+        assert_eq!(tracker.update(rivals), HashSet::from([ip]));
+        // Not in the scan in between: starts over
+        assert!(tracker.update([(ip, first, Some("laptop"))]).is_empty());
+        assert!(tracker.update(rivals).is_empty());
+
+        // Wired and Wi-Fi NIC of the same known device
+        let mut tracker = ConflictTracker::default();
+        let nics = [(ip, first, Some("laptop")), (ip, second, Some("laptop"))];
+        assert!(tracker.update(nics).is_empty());
+        assert!(tracker.update(nics).is_empty());
+    }
+
+    #[test]
+    fn test_new_devices_start_unknown() {
+        let config = StateConfig::default();
+        let mut device = device();
+        device.status = first_status(false, false);
+        assert_eq!(device.status, DeviceStatus::Unknown);
+
+        // Confirmed by the next scan, quietly
+        assert!(transition(&mut device, SEEN, false, Utc::now(), &config).is_none());
+        assert_eq!(device.status, DeviceStatus::Online);
+
+        assert_eq!(first_status(true, true), DeviceStatus::Block);
+        assert_eq!(first_status(false, true), DeviceStatus::Conflict);
+    }
+}
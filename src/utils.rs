@@ -1,16 +1,21 @@
+use serde::{Deserialize, Serialize};
 use std::net::Ipv4Addr;
 use std::str::FromStr;
 use std::fmt;
 use std::process::Command;
-use std::fs;
-use std::collections::HashMap;
 use std::time::Duration;
-use dns_lookup::lookup_addr;
-use trust_dns_resolver::config::*;
-use trust_dns_resolver::TokioAsyncResolver;
 use tokio::time::timeout;
 
+use crate::dhcp::DhcpLeases;
+use crate::llmnr;
+use crate::mac::MacAddress;
+use crate::mdns;
+use crate::models::{HostnameAnswer, HostnameSource};
+use crate::netbios;
+use crate::reverse_dns::{self, ReverseDns};
+
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum ParseError {
     InvalidFormat,
     InvalidIp,
@@ -60,11 +65,11 @@ pub fn parse_ip_range(range_str: &str) -> Result<Vec<Ipv4Addr>, ParseError> {
 pub fn flush_arp_cache(interface: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
     let output = if let Some(iface) = interface {
         Command::new("ip")
-            .args(&["neigh", "flush", "dev", iface])
+            .args(["neigh", "flush", "dev", iface])
             .output()?
     } else {
         Command::new("ip")
-            .args(&["neigh", "flush", "all"])
+            .args(["neigh", "flush", "all"])
             .output()?
     };
 
@@ -76,175 +81,228 @@ pub fn flush_arp_cache(interface: Option<&str>) -> Result<(), Box<dyn std::error
     Ok(())
 }
 
-/// Load DHCP leases from common locations
-/// Returns a HashMap of IP -> Hostname mappings
-pub fn load_dhcp_leases() -> HashMap<String, String> {
-    let mut leases = HashMap::new();
-
-    // Common DHCP lease file locations
-    let lease_files = vec![
-        "/var/lib/dhcp/dhcpd.leases",           // ISC DHCP Server
-        "/var/lib/dhcpd/dhcpd.leases",          // ISC DHCP Server (alternative)
-        "/tmp/dhcp.leases",                     // dnsmasq
-        "/var/lib/misc/dnsmasq.leases",         // dnsmasq (Debian/Ubuntu)
-        "/var/lib/dnsmasq/dnsmasq.leases",      // dnsmasq (alternative)
-        "/etc/pihole/dhcp.leases",              // Pi-hole
-    ];
-
-    for file_path in lease_files {
-        if let Ok(content) = fs::read_to_string(file_path) {
-            // Try parsing as dnsmasq format first
-            // Format: timestamp mac ip hostname client-id
-            // Example: 1234567890 aa:bb:cc:dd:ee:ff 192.168.1.100 mylaptop *
-            for line in content.lines() {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 4 {
-                    let ip = parts[2];
-                    let hostname = parts[3];
-                    if hostname != "*" && !hostname.is_empty() {
-                        leases.insert(ip.to_string(), hostname.to_string());
-                    }
-                }
-            }
-
-            // Try parsing as ISC DHCP format
-            // Format: lease 192.168.1.100 { ... client-hostname "mylaptop"; ... }
-            let mut current_ip: Option<String> = None;
-            for line in content.lines() {
-                let trimmed = line.trim();
-
-                if trimmed.starts_with("lease ") {
-                    // Extract IP from "lease 192.168.1.100 {"
-                    if let Some(ip_str) = trimmed.strip_prefix("lease ") {
-                        if let Some(ip) = ip_str.split_whitespace().next() {
-                            current_ip = Some(ip.to_string());
-                        }
-                    }
-                } else if let Some(ip) = &current_ip {
-                    // Look for client-hostname "name";
-                    if trimmed.starts_with("client-hostname") {
-                        if let Some(name_part) = trimmed.strip_prefix("client-hostname") {
-                            let name = name_part
-                                .trim()
-                                .trim_matches('"')
-                                .trim_matches(';')
-                                .trim();
-                            if !name.is_empty() {
-                                leases.insert(ip.clone(), name.to_string());
-                            }
-                        }
-                    }
-                } else if trimmed == "}" {
-                    current_ip = None;
-                }
-            }
+/// Try NetBIOS name lookup (for Windows devices)
+/// Sends a native node status query; the answer keeps the workgroup and the MAC the host
+/// reports, to be checked against the ARP result
+async fn try_netbios_lookup(ip: Ipv4Addr, wait: Duration) -> Option<HostnameAnswer> {
+    let status = netbios::node_status(ip, wait).await?;
+
+    // Filter out special names
+    let hostname = status
+        .name
+        .clone()
+        .filter(|name| !name.starts_with('_') && !name.starts_with(".."))?;
+    Some(HostnameAnswer {
+        netbios: Some(status),
+        ..HostnameAnswer::new(hostname, HostnameSource::Netbios)
+    })
+}
+
+/// Order in which hostname sources are tried
+/// DHCP leases first (fastest, most reliable for local networks), NetBIOS last (legacy Windows)
+pub const DEFAULT_HOSTNAME_CHAIN: [HostnameSource; 5] = [
+    HostnameSource::Dhcp,
+    HostnameSource::Dns,
+    HostnameSource::Mdns,
+    HostnameSource::Llmnr,
+    HostnameSource::Netbios,
+];
+
+/// Whether the resolver chain stops at the first name or asks every source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResolveMode {
+    #[default]
+    First,
+    /// Keep every name found, e.g. the DNS name and the NetBIOS name side by side
+    All,
+}
+
+/// One source of the resolver chain
+/// Written either as a plain source name ("dns") or as an object with a timeout
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ResolverStepRepr")]
+pub struct ResolverStep {
+    pub source: HostnameSource,
+    /// How long this source may take, in milliseconds (None uses the source's default)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ResolverStepRepr {
+    Source(HostnameSource),
+    Step {
+        source: HostnameSource,
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+}
+
+impl From<ResolverStepRepr> for ResolverStep {
+    fn from(repr: ResolverStepRepr) -> Self {
+        match repr {
+            ResolverStepRepr::Source(source) => Self {
+                source,
+                timeout_ms: None,
+            },
+            ResolverStepRepr::Step { source, timeout_ms } => Self { source, timeout_ms },
         }
     }
+}
 
-    leases
+impl ResolverStep {
+    /// How long this step may take: its own timeout or the source's default
+    pub fn timeout(&self) -> Duration {
+        self.timeout_ms
+            .map_or_else(|| default_timeout(self.source), Duration::from_millis)
+    }
 }
 
-/// Try NetBIOS name lookup (for Windows devices)
-/// Uses nmblookup command if available
-fn try_netbios_lookup(ip: &str) -> Option<String> {
-    // Try nmblookup -A <ip> to get NetBIOS name
-    let output = Command::new("nmblookup")
-        .args(&["-A", ip])
-        .output()
-        .ok()?;
-
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        // Parse output looking for the computer name
-        // Format: "    COMPUTERNAME    <00> -         B <ACTIVE>"
-        for line in stdout.lines() {
-            if line.contains("<00>") && line.contains("ACTIVE") && !line.contains("GROUP") {
-                // Extract the name (first field before <00>)
-                let parts: Vec<&str> = line.trim().split_whitespace().collect();
-                if !parts.is_empty() {
-                    let name = parts[0].trim();
-                    // Filter out special names and non-computer names
-                    if !name.starts_with('_') && !name.starts_with("..") && name.len() > 0 {
-                        return Some(name.to_string());
-                    }
-                }
-            }
+/// How long a source may take when its chain step sets no timeout
+fn default_timeout(source: HostnameSource) -> Duration {
+    match source {
+        HostnameSource::Dns => reverse_dns::DEFAULT_TIMEOUT,
+        HostnameSource::Mdns => mdns::DEFAULT_LISTEN_WINDOW,
+        HostnameSource::Llmnr => llmnr::DEFAULT_TIMEOUT,
+        HostnameSource::Netbios => netbios::DEFAULT_TIMEOUT,
+        // Leases are already in memory, the other sources are never queried by the chain
+        _ => Duration::ZERO,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResolverConfig {
+    /// Sources to run, in order. Sources left out are never queried
+    pub chain: Vec<ResolverStep>,
+    pub mode: ResolveMode,
+    /// Upper bound for resolving one device, in seconds
+    /// Unset, every step of the chain may run out its own timeout (see `budget`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            chain: DEFAULT_HOSTNAME_CHAIN
+                .iter()
+                .map(|&source| ResolverStep {
+                    source,
+                    timeout_ms: None,
+                })
+                .collect(),
+            mode: ResolveMode::First,
+            timeout: None,
         }
     }
-    None
 }
 
-/// Resolve hostname using multiple methods: DHCP leases, DNS reverse lookup, mDNS, and NetBIOS
+impl ResolverConfig {
+    /// Time allowed for resolving one device
+    /// Defaults to the sum of the step timeouts, so `all` mode isn't cut short when every
+    /// source is slow
+    pub fn budget(&self) -> Duration {
+        match self.timeout {
+            Some(secs) => Duration::from_secs(secs),
+            None => self.chain.iter().map(ResolverStep::timeout).sum(),
+        }
+    }
+}
+
+/// Resolve hostname using multiple methods: DHCP leases, DNS reverse lookup, mDNS, LLMNR and NetBIOS
 /// This improves hostname detection in home networks where DNS PTR records don't exist
-pub async fn resolve_hostname_with_timeout(
+/// Returns the answers in chain order (at most one unless the mode is `all`)
+pub async fn resolve_hostnames(
     ip: std::net::IpAddr,
-    dhcp_leases: Option<&HashMap<String, String>>,
-    timeout_seconds: u64,
-) -> Option<String> {
+    dhcp_leases: Option<&DhcpLeases>,
+    reverse_dns: &ReverseDns,
+    arp_mac: Option<MacAddress>,
+    resolver: &ResolverConfig,
+) -> Vec<HostnameAnswer> {
+    let mut answers = Vec::new();
     // Wrap the actual resolution in a timeout
-    match timeout(
-        Duration::from_secs(timeout_seconds),
-        resolve_hostname_impl(ip, dhcp_leases),
+    // Names found before the timeout are kept
+    let _ = timeout(
+        resolver.budget(),
+        resolve_hostnames_impl(ip, dhcp_leases, reverse_dns, arp_mac, resolver, &mut answers),
     )
-    .await
-    {
-        Ok(result) => result,
-        Err(_) => None, // Timeout occurred
-    }
+    .await;
+    answers
 }
 
 /// Internal implementation of hostname resolution
-async fn resolve_hostname_impl(
+async fn resolve_hostnames_impl(
     ip: std::net::IpAddr,
-    dhcp_leases: Option<&HashMap<String, String>>,
-) -> Option<String> {
-    let ip_str = ip.to_string();
-
-    // 0. Try DHCP leases first (fastest, most reliable for local networks)
-    if let Some(leases) = dhcp_leases {
-        if let Some(hostname) = leases.get(&ip_str) {
-            return Some(hostname.clone());
+    dhcp_leases: Option<&DhcpLeases>,
+    reverse_dns: &ReverseDns,
+    arp_mac: Option<MacAddress>,
+    resolver: &ResolverConfig,
+    answers: &mut Vec<HostnameAnswer>,
+) {
+    for step in &resolver.chain {
+        let Some(answer) =
+            lookup_hostname(step.source, ip, dhcp_leases, reverse_dns, arp_mac, step.timeout()).await
+        else {
+            continue;
+        };
+
+        if resolver.mode == ResolveMode::First {
+            answers.push(answer);
+            return;
         }
-    }
-
-    // 1. Try standard DNS reverse lookup (fast)
-    if let Ok(hostname) = lookup_addr(&ip) {
-        // Filter out IP addresses returned as hostnames
-        if !hostname.chars().next()?.is_numeric() {
-            return Some(hostname);
+        // The same name from several sources is only listed once
+        if !answers.iter().any(|a| a.hostname == answer.hostname) {
+            answers.push(answer);
         }
     }
+}
 
-    // 2. Try mDNS (.local) resolution
-    // Create a resolver configured for mDNS
-    let resolver = TokioAsyncResolver::tokio(
-        ResolverConfig::default(),
-        ResolverOpts::default()
-    );
-
-    // Try querying common .local patterns
-    let ip_parts: Vec<String> = ip.to_string().split('.').map(|s| s.to_string()).collect();
-
-    // Try patterns like: ip-192-168-1-100.local
-    let patterns = vec![
-        format!("ip-{}-{}-{}-{}.local", ip_parts[0], ip_parts[1], ip_parts[2], ip_parts[3]),
-        format!("{}-{}-{}-{}.local", ip_parts[0], ip_parts[1], ip_parts[2], ip_parts[3]),
-    ];
-
-    for pattern in patterns {
-        if let Ok(response) = resolver.lookup_ip(pattern.clone()).await {
-            if response.iter().any(|resolved_ip| resolved_ip == ip) {
-                return Some(pattern.trim_end_matches(".local").to_string());
-            }
+/// Resolve a hostname through a single source
+/// Used by the resolver chain and by the `lookup` command to test one method at a time
+pub async fn lookup_hostname(
+    source: HostnameSource,
+    ip: std::net::IpAddr,
+    dhcp_leases: Option<&DhcpLeases>,
+    reverse_dns: &ReverseDns,
+    arp_mac: Option<MacAddress>,
+    wait: Duration,
+) -> Option<HostnameAnswer> {
+    let hostname = match (source, ip) {
+        // Lease hostnames are ignored when the lease belongs to another MAC
+        (HostnameSource::Dhcp, std::net::IpAddr::V4(ipv4)) => {
+            dhcp_leases?.hostname_for(&ipv4, arp_mac)
         }
-    }
-
-    // 3. Try NetBIOS lookup (for Windows devices)
-    if let Some(netbios_name) = try_netbios_lookup(&ip.to_string()) {
-        return Some(netbios_name);
-    }
+        // Standard DNS reverse lookup, through the configured servers
+        (HostnameSource::Dns, _) => {
+            let answer = timeout(wait, reverse_dns.lookup(ip)).await.ok()??;
+            return Some(HostnameAnswer {
+                hostname: answer.hostname,
+                source,
+                fcrdns: answer.fcrdns,
+                netbios: None,
+            });
+        }
+        // Multicast DNS reverse lookup (Apple, Chromecast, avahi hosts)
+        (HostnameSource::Mdns, std::net::IpAddr::V4(ipv4)) => {
+            mdns::reverse_lookup(ipv4, wait).await
+        }
+        // LLMNR reverse lookup (Windows hosts without NetBIOS)
+        (HostnameSource::Llmnr, std::net::IpAddr::V4(ipv4)) => {
+            llmnr::reverse_lookup(ipv4, wait).await
+        }
+        // NetBIOS node status (legacy Windows devices, Samba)
+        (HostnameSource::Netbios, std::net::IpAddr::V4(ipv4)) => {
+            return try_netbios_lookup(ipv4, wait).await;
+        }
+        // SNMP names are filled in by the scanner's SNMP pass, sniffed names by the
+        // monitor's DHCP listener, manual names only come from the hostname cache
+        _ => None,
+    };
 
-    None
+    hostname.map(|hostname| HostnameAnswer::new(hostname, source))
 }
 
 #[cfg(test)]
@@ -263,4 +321,40 @@ mod tests {
     fn test_parse_invalid_format() {
         assert!(parse_ip_range("192.168.1.1").is_err());
     }
+
+    #[test]
+    fn test_resolver_config_steps() {
+        let config: ResolverConfig = serde_json::from_str(
+            r#"{ "chain": ["dhcp", { "source": "netbios", "timeout_ms": 300 }], "mode": "all" }"#,
+        )
+        .unwrap();
+        assert_eq!(config.mode, ResolveMode::All);
+        assert_eq!(config.timeout, None);
+        assert_eq!(
+            config.chain,
+            vec![
+                ResolverStep { source: HostnameSource::Dhcp, timeout_ms: None },
+                ResolverStep { source: HostnameSource::Netbios, timeout_ms: Some(300) },
+            ]
+        );
+        assert_eq!(config.budget(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_resolver_budget_covers_chain() {
+        let config = ResolverConfig::default();
+        // DNS, mDNS, LLMNR and NetBIOS may all run out their timeouts in `all` mode
+        let steps: Duration = [
+            reverse_dns::DEFAULT_TIMEOUT,
+            mdns::DEFAULT_LISTEN_WINDOW,
+            llmnr::DEFAULT_TIMEOUT,
+            netbios::DEFAULT_TIMEOUT,
+        ]
+        .into_iter()
+        .sum();
+        assert_eq!(config.budget(), steps);
+
+        let config: ResolverConfig = serde_json::from_str(r#"{ "timeout": 2 }"#).unwrap();
+        assert_eq!(config.budget(), Duration::from_secs(2));
+    }
 }
@@ -1,194 +1,387 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::OnceLock;
 
-/// OUI (Organizationally Unique Identifier) Vendor Lookup
-/// The first 3 bytes (24 bits) of a MAC address identify the manufacturer
+use crate::mac::MacAddress;
+use crate::oui::{self, VendorConfig};
+use crate::registry;
+
+/// User-maintained prefixes, next to devices.json
+pub const CUSTOM_VENDORS_FILE: &str = "custom_vendors.json";
+
+/// Block sizes assigned by the IEEE, longest first: MA-S/IAB (36 bits), MA-M (28), MA-L (24)
+const PREFIX_BITS: [u32; 3] = [36, 28, 24];
+
+/// What kind of organization a MAC prefix belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VendorCategory {
+    Virtualization,
+    Networking,
+    Computer,
+    Mobile,
+    Iot,
+    /// Software-assigned addresses of bridges and virtual interfaces (custom entries)
+    LocallyAdministered,
+    /// No registered vendor and the locally administered bit is set: private client MACs
+    /// that phones and laptops rotate (hypervisor prefixes are listed as virtualization)
+    Randomized,
+    Other,
+}
+
+impl VendorCategory {
+    /// MACs of VMs, containers and virtual interfaces rather than physical hardware
+    pub fn is_virtual(self) -> bool {
+        matches!(self, VendorCategory::Virtualization | VendorCategory::LocallyAdministered)
+    }
+}
+
+impl fmt::Display for VendorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            VendorCategory::Virtualization => "virtualization",
+            VendorCategory::Networking => "networking",
+            VendorCategory::Computer => "computer",
+            VendorCategory::Mobile => "mobile",
+            VendorCategory::Iot => "IoT",
+            VendorCategory::LocallyAdministered => "locally administered",
+            VendorCategory::Randomized => "randomized",
+            VendorCategory::Other => "other",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Vendor of a MAC prefix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VendorInfo {
+    pub name: Cow<'static, str>,
+    /// First word of the name, for narrow columns (e.g. "Cisco")
+    pub short_name: Cow<'static, str>,
+    pub category: VendorCategory,
+    /// Set when the entry comes from custom_vendors.json
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub custom: bool,
+}
+
+impl VendorInfo {
+    fn new(name: String, category: VendorCategory) -> Self {
+        let short_name = name
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches([',', '.'])
+            .to_string();
+        Self {
+            name: Cow::Owned(name),
+            short_name: Cow::Owned(short_name),
+            category,
+            custom: false,
+        }
+    }
+}
+
+/// What a MAC address says about itself
+#[derive(Debug, Serialize)]
+pub struct MacReport<'a> {
+    /// Uppercase, colon separated
+    pub mac: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<&'a VendorInfo>,
+    /// Length of the registered prefix that matched, in bits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix_bits: Option<u32>,
+    /// I/G bit: group (multicast) rather than individual (unicast) address
+    pub multicast: bool,
+    /// U/L bit: locally administered rather than globally unique
+    pub local: bool,
+}
+
+/// Entry of custom_vendors.json
+/// Format: [{"prefix": "00:1B:C5:0A", "name": "Acme Sensors", "category": "iot"}]
+/// The prefix is any number of hex digits, or a MAC with a bit length ("00:1B:C5:0A:80:00/26")
+#[derive(Debug, Deserialize)]
+struct CustomVendor {
+    prefix: String,
+    name: String,
+    /// Defaults to the category of the entry being overridden, or one guessed from the name
+    #[serde(default)]
+    category: Option<VendorCategory>,
+}
+
+/// Category of a registry organization name, from the keywords shared with build.rs
+fn categorize(name: &str) -> VendorCategory {
+    match registry::category_of(name) {
+        "virtualization" => VendorCategory::Virtualization,
+        "networking" => VendorCategory::Networking,
+        "computer" => VendorCategory::Computer,
+        "mobile" => VendorCategory::Mobile,
+        "iot" => VendorCategory::Iot,
+        _ => VendorCategory::Other,
+    }
+}
+
+/// Compiled-in vendors per block size, each sorted by prefix (generated by build.rs)
+/// MA-L and MA-M prefixes fit in a u32; MA-S prefixes are 36 bits wide, so they take a u64
+struct BuiltinTable {
+    ma_l: &'static [(u32, VendorInfo)],
+    ma_m: &'static [(u32, VendorInfo)],
+    ma_s: &'static [(u64, VendorInfo)],
+}
+
+impl BuiltinTable {
+    /// Vendor registered for exactly this MA-L, MA-M or MA-S prefix
+    fn get(&self, value: u64, bits: u32) -> Option<&'static VendorInfo> {
+        match bits {
+            24 => find(self.ma_l, u32::try_from(value).ok()?),
+            28 => find(self.ma_m, u32::try_from(value).ok()?),
+            36 => find(self.ma_s, value),
+            _ => None,
+        }
+    }
+}
+
+/// Binary search of a sorted table
+fn find<K: Ord>(table: &'static [(K, VendorInfo)], prefix: K) -> Option<&'static VendorInfo> {
+    table
+        .binary_search_by(|(key, _)| key.cmp(&prefix))
+        .ok()
+        .map(|i| &table[i].1)
+}
 
+include!(concat!(env!("OUT_DIR"), "/builtin_vendors.rs"));
+
+/// Locally administered MACs without a known hypervisor prefix
+static RANDOMIZED: VendorInfo = VendorInfo {
+    name: Cow::Borrowed("Randomized/Private MAC"),
+    short_name: Cow::Borrowed("Randomized"),
+    category: VendorCategory::Randomized,
+    custom: false,
+};
+
+/// Process-wide database, see `VendorDb::shared`
+static SHARED: OnceLock<VendorDb> = OnceLock::new();
+
+/// OUI (Organizationally Unique Identifier) Vendor Lookup
+/// The first 3 bytes (24 bits) of a MAC address identify the manufacturer, except inside
+/// OUIs the IEEE splits into smaller MA-M and MA-S blocks, so the longest prefix wins
 pub struct VendorDb {
-    oui_map: HashMap<String, &'static str>,
+    /// Registry and custom entries loaded at runtime, on top of the compiled-in table:
+    /// prefix length in bits -> prefix value (the MAC's top bits) -> vendor
+    prefixes: HashMap<u32, HashMap<u64, VendorInfo>>,
+    /// Prefix lengths in use, longest first
+    lengths: Vec<u32>,
+    /// Compiled-in entries, below the runtime ones
+    builtin: &'static BuiltinTable,
 }
 
 impl VendorDb {
+    /// Vendor database with the built-in table only
     pub fn new() -> Self {
-        let mut oui_map = HashMap::new();
-
-        // Virtualization / Cloud Vendors
-        oui_map.insert("00:15:5D".to_string(), "Microsoft Hyper-V");
-        oui_map.insert("00:50:56".to_string(), "VMware");
-        oui_map.insert("00:0C:29".to_string(), "VMware");
-        oui_map.insert("00:05:69".to_string(), "VMware");
-        oui_map.insert("00:1C:14".to_string(), "VMware");
-        oui_map.insert("52:54:00".to_string(), "QEMU/KVM Virtual NIC");
-        oui_map.insert("BC:24:11".to_string(), "Proxmox Virtual Machine");
-        oui_map.insert("00:16:3E".to_string(), "Xen Virtual Machine");
-        oui_map.insert("08:00:27".to_string(), "Oracle VirtualBox");
-        oui_map.insert("00:21:F6".to_string(), "Oracle VirtualBox");
-
-        // Network Equipment Vendors
-        oui_map.insert("00:00:0C".to_string(), "Cisco Systems");
-        oui_map.insert("00:01:42".to_string(), "Cisco Systems");
-        oui_map.insert("00:01:43".to_string(), "Cisco Systems");
-        oui_map.insert("00:01:96".to_string(), "Cisco Systems");
-        oui_map.insert("00:01:97".to_string(), "Cisco Systems");
-        oui_map.insert("00:01:C7".to_string(), "Cisco Systems");
-        oui_map.insert("00:02:3D".to_string(), "Cisco Systems");
-        oui_map.insert("00:02:4A".to_string(), "Cisco Systems");
-        oui_map.insert("00:02:4B".to_string(), "Cisco Systems");
-        oui_map.insert("00:50:F2".to_string(), "Microsoft Corporation");
-        oui_map.insert("AC:DE:48".to_string(), "Ubiquiti Networks");
-        oui_map.insert("DC:9F:DB".to_string(), "Ubiquiti Networks");
-        oui_map.insert("F0:9F:C2".to_string(), "Ubiquiti Networks");
-        oui_map.insert("68:D7:9A".to_string(), "Ubiquiti Networks");
-        oui_map.insert("24:A4:3C".to_string(), "Ubiquiti Networks");
-        oui_map.insert("E4:38:83".to_string(), "TP-Link");
-        oui_map.insert("98:DE:D0".to_string(), "TP-Link");
-        oui_map.insert("50:C7:BF".to_string(), "TP-Link");
-        oui_map.insert("A4:2B:B0".to_string(), "TP-Link");
-
-        // Computer Manufacturers
-        oui_map.insert("00:50:B6".to_string(), "Dell");
-        oui_map.insert("00:14:22".to_string(), "Dell");
-        oui_map.insert("D4:BE:D9".to_string(), "Dell");
-        oui_map.insert("D0:67:E5".to_string(), "Dell");
-        oui_map.insert("18:03:73".to_string(), "Dell");
-        oui_map.insert("00:1B:21".to_string(), "Dell");
-        oui_map.insert("00:15:C5".to_string(), "Dell");
-        oui_map.insert("B8:CA:3A".to_string(), "Dell");
-        oui_map.insert("3C:D9:2B".to_string(), "Hewlett Packard");
-        oui_map.insert("00:1F:29".to_string(), "Hewlett Packard");
-        oui_map.insert("00:1E:0B".to_string(), "Hewlett Packard");
-        oui_map.insert("00:24:81".to_string(), "Hewlett Packard");
-        oui_map.insert("00:26:55".to_string(), "Hewlett Packard");
-        oui_map.insert("D4:85:64".to_string(), "Hewlett Packard");
-        oui_map.insert("EC:B1:D7".to_string(), "Hewlett Packard");
-        oui_map.insert("00:03:93".to_string(), "Apple");
-        oui_map.insert("00:05:02".to_string(), "Apple");
-        oui_map.insert("00:0A:27".to_string(), "Apple");
-        oui_map.insert("00:0A:95".to_string(), "Apple");
-        oui_map.insert("00:0D:93".to_string(), "Apple");
-        oui_map.insert("00:16:CB".to_string(), "Apple");
-        oui_map.insert("00:17:F2".to_string(), "Apple");
-        oui_map.insert("00:19:E3".to_string(), "Apple");
-        oui_map.insert("00:1B:63".to_string(), "Apple");
-        oui_map.insert("00:1C:B3".to_string(), "Apple");
-        oui_map.insert("00:1D:4F".to_string(), "Apple");
-        oui_map.insert("00:1E:52".to_string(), "Apple");
-        oui_map.insert("00:1F:5B".to_string(), "Apple");
-        oui_map.insert("00:1F:F3".to_string(), "Apple");
-        oui_map.insert("00:21:E9".to_string(), "Apple");
-        oui_map.insert("00:22:41".to_string(), "Apple");
-        oui_map.insert("00:23:12".to_string(), "Apple");
-        oui_map.insert("00:23:32".to_string(), "Apple");
-        oui_map.insert("00:23:6C".to_string(), "Apple");
-        oui_map.insert("00:23:DF".to_string(), "Apple");
-        oui_map.insert("00:24:36".to_string(), "Apple");
-        oui_map.insert("00:25:00".to_string(), "Apple");
-        oui_map.insert("00:25:4B".to_string(), "Apple");
-        oui_map.insert("00:25:BC".to_string(), "Apple");
-        oui_map.insert("00:26:08".to_string(), "Apple");
-        oui_map.insert("00:26:4A".to_string(), "Apple");
-        oui_map.insert("00:26:B0".to_string(), "Apple");
-        oui_map.insert("00:26:BB".to_string(), "Apple");
-        oui_map.insert("04:0C:CE".to_string(), "Apple");
-        oui_map.insert("04:15:52".to_string(), "Apple");
-        oui_map.insert("0C:3E:9F".to_string(), "Apple");
-        oui_map.insert("10:DD:B1".to_string(), "Apple");
-        oui_map.insert("18:E7:F4".to_string(), "Apple");
-        oui_map.insert("28:CF:E9".to_string(), "Apple");
-        oui_map.insert("30:05:5C".to_string(), "Lenovo");
-        oui_map.insert("00:21:CC".to_string(), "Lenovo");
-        oui_map.insert("00:1F:16".to_string(), "Lenovo");
-        oui_map.insert("54:EE:75".to_string(), "Lenovo");
-        oui_map.insert("B8:AC:6F".to_string(), "Lenovo");
-
-        // Network Interface Manufacturers
-        oui_map.insert("00:E0:4C".to_string(), "Realtek");
-        oui_map.insert("00:0C:76".to_string(), "Realtek");
-        oui_map.insert("52:54:00".to_string(), "Realtek (or QEMU)");
-        oui_map.insert("D8:0D:17".to_string(), "Realtek");
-        oui_map.insert("E8:6A:64".to_string(), "Realtek");
-        oui_map.insert("00:13:3B".to_string(), "Intel");
-        oui_map.insert("00:15:17".to_string(), "Intel");
-        oui_map.insert("00:1B:21".to_string(), "Intel");
-        oui_map.insert("00:1E:67".to_string(), "Intel");
-        oui_map.insert("00:21:5C".to_string(), "Intel");
-        oui_map.insert("00:23:15".to_string(), "Intel");
-        oui_map.insert("00:25:64".to_string(), "Intel");
-        oui_map.insert("68:5B:35".to_string(), "Intel");
-        oui_map.insert("D0:94:66".to_string(), "Intel");
-        oui_map.insert("00:60:B0".to_string(), "Hewlett Packard");
-        oui_map.insert("00:11:0A".to_string(), "Hewlett Packard");
-        oui_map.insert("00:15:60".to_string(), "Hewlett Packard");
-        oui_map.insert("00:17:A4".to_string(), "Hewlett Packard");
-        oui_map.insert("00:1A:4B".to_string(), "Hewlett Packard");
-        oui_map.insert("00:1E:0B".to_string(), "Hewlett Packard");
-        oui_map.insert("00:21:5A".to_string(), "Hewlett Packard");
-        oui_map.insert("00:23:7D".to_string(), "Hewlett Packard");
-        oui_map.insert("00:25:B3".to_string(), "Hewlett Packard");
-        oui_map.insert("00:26:55".to_string(), "Hewlett Packard");
-
-        // Raspberry Pi
-        oui_map.insert("B8:27:EB".to_string(), "Raspberry Pi");
-        oui_map.insert("DC:A6:32".to_string(), "Raspberry Pi");
-        oui_map.insert("E4:5F:01".to_string(), "Raspberry Pi");
-
-        // Common Routers
-        oui_map.insert("24:2F:D0".to_string(), "Intelbras");
-        oui_map.insert("64:1C:67".to_string(), "Intelbras");
-        oui_map.insert("B0:19:21".to_string(), "Intelbras");
-        oui_map.insert("CA:4E:2B".to_string(), "Unknown (Private/Virtual)");
-
-        // Android/Samsung
-        oui_map.insert("98:5A:EB".to_string(), "Samsung Electronics");
-        oui_map.insert("8C:3B:AD".to_string(), "Samsung Electronics");
-        oui_map.insert("44:07:0B".to_string(), "Samsung Electronics");
-
-        Self { oui_map }
-    }
-
-    /// Lookup vendor by MAC address
-    pub fn lookup(&self, mac: &str) -> Option<String> {
-        // Normalize MAC address and extract OUI (first 3 bytes)
-        let normalized = mac.to_uppercase().replace("-", ":");
-        let parts: Vec<&str> = normalized.split(':').collect();
-
-        if parts.len() < 3 {
-            return None;
+        Self::with_builtin(&BUILTIN)
+    }
+
+    fn with_builtin(builtin: &'static BuiltinTable) -> Self {
+        Self {
+            prefixes: HashMap::new(),
+            lengths: Vec::new(),
+            builtin,
         }
+    }
 
-        // Try exact OUI match (XX:XX:XX)
-        let oui = format!("{}:{}:{}", parts[0], parts[1], parts[2]);
+    /// Database loaded once (on first use) and shared by every scan
+    pub fn shared(config: &VendorConfig) -> &'static VendorDb {
+        SHARED.get_or_init(|| Self::load(config))
+    }
 
-        if let Some(vendor) = self.oui_map.get(&oui) {
-            return Some(vendor.to_string());
+    /// Built-in table, extended (and overridden) by the IEEE/Wireshark registry files,
+    /// the local cache filled by `vendor update` and finally custom_vendors.json
+    pub fn load(config: &VendorConfig) -> Self {
+        let mut db = Self::new();
+        for (prefix, name) in oui::load(config) {
+            // Registry names don't say a prefix is virtual ("Microsoft Corporation" for Hyper-V),
+            // so built-in categories are kept
+            let category = parse_hex(&prefix)
+                .and_then(|(value, bits)| db.exact(value, bits))
+                .map_or_else(|| categorize(&name), |info| info.category);
+            db.insert(&prefix, name, category);
         }
 
-        // Check if it's a locally administered address (virtual/private)
-        if let Ok(first_byte) = u8::from_str_radix(parts[0], 16) {
-            if first_byte & 0x02 != 0 {
-                return Some("Virtual/Private MAC".to_string());
-            }
+        match load_custom_vendors() {
+            Ok(entries) => db.apply_custom(entries),
+            Err(e) => eprintln!("Warning: ignoring {}: {}", CUSTOM_VENDORS_FILE, e),
+        }
+        db
+    }
+
+    fn apply_custom(&mut self, entries: Vec<CustomVendor>) {
+        for entry in entries {
+            let Some((value, bits)) = parse_prefix(&entry.prefix) else {
+                eprintln!("Warning: invalid prefix '{}' in {}", entry.prefix, CUSTOM_VENDORS_FILE);
+                continue;
+            };
+            let category = entry.category.unwrap_or_else(|| {
+                self.exact(value, bits)
+                    .map_or_else(|| categorize(&entry.name), |info| info.category)
+            });
+            let mut info = VendorInfo::new(entry.name, category);
+            info.custom = true;
+            self.insert_bits(value, bits, info);
         }
+    }
 
-        None
+    /// Add a vendor for a prefix of 6, 7 or 9 hex digits (separators allowed)
+    fn insert(&mut self, prefix: &str, name: String, category: VendorCategory) {
+        let Some((value, bits)) = parse_hex(prefix) else {
+            return;
+        };
+        if PREFIX_BITS.contains(&bits) {
+            self.insert_bits(value, bits, VendorInfo::new(name, category));
+        }
+    }
+
+    fn insert_bits(&mut self, value: u64, bits: u32, info: VendorInfo) {
+        if !self.lengths.contains(&bits) {
+            self.lengths.push(bits);
+            self.lengths.sort_unstable_by(|a, b| b.cmp(a));
+        }
+        self.prefixes.entry(bits).or_default().insert(value, info);
+    }
+
+    /// Entry registered for exactly this prefix
+    fn exact(&self, value: u64, bits: u32) -> Option<&VendorInfo> {
+        self.prefixes
+            .get(&bits)
+            .and_then(|map| map.get(&value))
+            .or_else(|| self.builtin.get(value, bits))
+    }
+
+    /// Vendor and length of the longest registered prefix covering `mac` (at least 24 bits known)
+    /// Runtime entries win over built-in ones of the same length
+    fn longest_match(&self, mac: u64, known_bits: u32) -> Option<(u32, &VendorInfo)> {
+        let runtime = |bits: u32| Some((bits, self.prefixes.get(&bits)?.get(&(mac >> (48 - bits)))?));
+        (24..=known_bits)
+            .rev()
+            .filter(|bits| PREFIX_BITS.contains(bits) || self.lengths.contains(bits))
+            .find_map(|bits| runtime(bits).or_else(|| Some((bits, self.builtin.get(mac >> (48 - bits), bits)?))))
+            .or_else(|| self.lengths.iter().copied().filter(|&bits| bits < 24).find_map(runtime))
+    }
+
+    /// Lookup vendor by MAC address, without allocating
+    pub fn lookup(&self, mac: MacAddress) -> Option<&VendorInfo> {
+        self.resolve(mac.to_u64(), 48)?.1
+    }
+
+    /// Matched prefix length and vendor of a parsed MAC, None if too short or too long
+    fn resolve(&self, value: u64, digits_bits: u32) -> Option<(Option<u32>, Option<&VendorInfo>)> {
+        if !(24..=48).contains(&digits_bits) {
+            return None;
+        }
+        // Left-align partial MACs in 48 bits
+        let mac_value = value << (48 - digits_bits);
+
+        Some(match self.longest_match(mac_value, digits_bits) {
+            Some((bits, vendor)) => (Some(bits), Some(vendor)),
+            // Locally administered without a known hypervisor prefix: a private client MAC
+            None if (mac_value >> 40) & 0x02 != 0 => (None, Some(&RANDOMIZED)),
+            None => (None, None),
+        })
+    }
+
+    /// Vendor, matched prefix length and address bits of a MAC (or OUI) in any common format:
+    /// colon, dash or space separated, Cisco dotted ("bc24.1136.2d6e") or bare hex
+    /// A partial MAC (at least the OUI) matches the blocks it covers
+    pub fn describe(&self, mac: &str) -> Option<MacReport<'_>> {
+        let (value, digits_bits) = match mac.parse::<MacAddress>() {
+            Ok(mac) => (mac.to_u64(), 48),
+            Err(_) => parse_hex(mac)?,
+        };
+        let (prefix_bits, vendor) = self.resolve(value, digits_bits)?;
+        let first_octet = (value >> (digits_bits - 8)) as u8;
+
+        let hex = format!("{:0width$X}", value, width = (digits_bits / 4) as usize);
+        Some(MacReport {
+            mac: hex
+                .as_bytes()
+                .chunks(2)
+                .map(|pair| String::from_utf8_lossy(pair))
+                .collect::<Vec<_>>()
+                .join(":"),
+            vendor,
+            prefix_bits,
+            multicast: first_octet & 0x01 != 0,
+            local: first_octet & 0x02 != 0,
+        })
     }
 
     /// Check if MAC appears to be from a virtual machine
-    pub fn is_virtual(&self, mac: &str) -> bool {
-        if let Some(vendor) = self.lookup(mac) {
-            let vendor_lower = vendor.to_lowercase();
-            vendor_lower.contains("virtual")
-                || vendor_lower.contains("vmware")
-                || vendor_lower.contains("qemu")
-                || vendor_lower.contains("kvm")
-                || vendor_lower.contains("proxmox")
-                || vendor_lower.contains("hyper-v")
-                || vendor_lower.contains("xen")
-                || vendor_lower.contains("virtualbox")
-                || vendor_lower.contains("private")
-        } else {
-            false
+    pub fn is_virtual(&self, mac: MacAddress) -> bool {
+        self.lookup(mac).is_some_and(|info| info.category.is_virtual())
+    }
+
+    /// Check if MAC is a rotating private address rather than a VM or hardware MAC
+    pub fn is_randomized(&self, mac: MacAddress) -> bool {
+        self.lookup(mac)
+            .is_some_and(|info| info.category == VendorCategory::Randomized)
+    }
+}
+
+fn load_custom_vendors() -> Result<Vec<CustomVendor>, Box<dyn std::error::Error>> {
+    if !Path::new(CUSTOM_VENDORS_FILE).exists() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(CUSTOM_VENDORS_FILE)?;
+    let reader = BufReader::new(file);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Custom prefix as its value and length in bits: the hex digits given, or cut to "/bits"
+fn parse_prefix(prefix: &str) -> Option<(u64, u32)> {
+    let (digits, bits) = match prefix.split_once('/') {
+        Some((digits, bits)) => (digits, Some(bits.trim().parse::<u32>().ok()?)),
+        None => (prefix, None),
+    };
+    let (value, digit_bits) = parse_hex(digits.trim())?;
+    let bits = bits.unwrap_or(digit_bits);
+    if bits == 0 || bits > digit_bits {
+        return None;
+    }
+    Some((value >> (digit_bits - bits), bits))
+}
+
+/// Hex digits of a MAC or prefix as a number, with the number of bits they cover
+/// Bare hex, or groups split by a single kind of separator: 1-2 digits with ':', '-' or spaces
+/// (leading zeros may be dropped, "0:1b:21"), exactly 4 with Cisco dots ("001b.218d")
+fn parse_hex(text: &str) -> Option<(u64, u32)> {
+    let text = text.trim();
+    let separators: Vec<char> = [':', '-', '.', ' '].into_iter().filter(|&c| text.contains(c)).collect();
+    let digits = match separators[..] {
+        [] => text.to_string(),
+        ['.'] => {
+            let groups: Vec<&str> = text.split('.').collect();
+            if !groups.iter().all(|g| g.len() == 4) {
+                return None;
+            }
+            groups.concat()
+        }
+        [separator] => {
+            let groups: Vec<&str> = text.split(separator).collect();
+            if !groups.iter().all(|g| (1..=2).contains(&g.len())) {
+                return None;
+            }
+            groups.iter().map(|g| format!("{:0>2}", g)).collect()
         }
+        _ => return None,
+    };
+    if !(1..=12).contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
     }
+    Some((u64::from_str_radix(&digits, 16).ok()?, digits.len() as u32 * 4))
 }
 
 impl Default for VendorDb {
@@ -201,23 +394,194 @@ impl Default for VendorDb {
 mod tests {
     use super::*;
 
+    fn mac(text: &str) -> MacAddress {
+        text.parse().unwrap()
+    }
+
+    /// Vendor name of a MAC, or of an OUI/partial MAC
+    fn name(db: &VendorDb, mac: &str) -> Option<String> {
+        db.describe(mac)?.vendor.map(|info| info.name.to_string())
+    }
+
     #[test]
     fn test_lookup_proxmox() {
         let db = VendorDb::new();
-        assert_eq!(db.lookup("BC:24:11:36:2D:6E"), Some("Proxmox Virtual Machine".to_string()));
+        let info = db.lookup(mac("BC:24:11:36:2D:6E")).unwrap();
+        assert_eq!(info.name, "Proxmox Server Solutions GmbH");
+        assert_eq!(info.short_name, "Proxmox");
+        assert_eq!(info.category, VendorCategory::Virtualization);
     }
 
     #[test]
     fn test_lookup_intel() {
         let db = VendorDb::new();
-        assert_eq!(db.lookup("68:5B:35:8D:89:41"), Some("Intel".to_string()));
+        assert_eq!(name(&db, "00:1B:21:8D:89:41").as_deref(), Some("Intel Corporate"));
+    }
+
+    #[test]
+    fn test_registry_overrides_builtin() {
+        let dir = std::env::temp_dir().join(format!("getmacrede-oui-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("oui.csv"),
+            "Registry,Assignment,Organization Name,Organization Address\n\
+             MA-L,001B21,Intel Corporation,Santa Clara\n\
+             MA-L,A483E7,Apple Inc.,Cupertino\n\
+             MA-L,00155D,Microsoft Corporation,Redmond\n",
+        )
+        .unwrap();
+
+        let db = VendorDb::load(&VendorConfig {
+            registry_path: Some(dir.clone()),
+        });
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(name(&db, "00:1B:21:8D:89:41").as_deref(), Some("Intel Corporation"));
+        assert_eq!(name(&db, "a4-83-e7-12-34-56").as_deref(), Some("Apple Inc."));
+        assert_eq!(db.lookup(mac("a4-83-e7-12-34-56")).unwrap().category, VendorCategory::Computer);
+        // Registry names keep the built-in category
+        assert!(db.is_virtual(mac("00:15:5D:01:02:03")));
+        // Built-in entries missing from the registry are still known
+        assert_eq!(name(&db, "BC:24:11:36:2D:6E").as_deref(), Some("Proxmox Server Solutions GmbH"));
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let mut db = VendorDb::new();
+        db.insert("1C8879", "IEEE Registration Authority".to_string(), VendorCategory::Other);
+        db.insert("1C88797", "Sensys Networks, Inc.".to_string(), VendorCategory::Other);
+        db.insert("70B3D5F2C", "Vision Technologies".to_string(), VendorCategory::Other);
+
+        assert_eq!(name(&db, "1C:88:79:7A:00:01").as_deref(), Some("Sensys Networks, Inc."));
+        assert_eq!(name(&db, "1C:88:79:1A:00:01").as_deref(), Some("IEEE Registration Authority"));
+        assert_eq!(name(&db, "70-B3-D5-F2-C1-23").as_deref(), Some("Vision Technologies"));
+        assert_eq!(name(&db, "70b3.d5f2.d123").as_deref(), None);
+        // A bare OUI can't select a 28-bit block
+        assert_eq!(name(&db, "1C:88:79").as_deref(), Some("IEEE Registration Authority"));
+    }
+
+    #[test]
+    fn test_custom_vendors() {
+        let mut db = VendorDb::new();
+        let entries = serde_json::from_str(
+            r#"[
+                {"prefix": "00:1B:21:8D", "name": "Acme Lab Switch", "category": "networking"},
+                {"prefix": "B8:27:EB:00:00:00/25", "name": "Acme Sensor"},
+                {"prefix": "BC:24:11", "name": "Homelab VM"},
+                {"prefix": "xyz", "name": "Broken"}
+            ]"#,
+        )
+        .unwrap();
+        db.apply_custom(entries);
+
+        let info = db.lookup(mac("00:1B:21:8D:89:41")).unwrap();
+        assert_eq!(info.name, "Acme Lab Switch");
+        assert_eq!(info.category, VendorCategory::Networking);
+        assert!(info.custom);
+        // Outside the custom block the built-in entry still applies
+        assert_eq!(name(&db, "00:1B:21:8E:89:41").as_deref(), Some("Intel Corporate"));
+        assert!(!db.lookup(mac("00:1B:21:8E:89:41")).unwrap().custom);
+
+        assert_eq!(name(&db, "B8:27:EB:7F:00:01").as_deref(), Some("Acme Sensor"));
+        assert_eq!(name(&db, "B8:27:EB:80:00:01").as_deref(), Some("Raspberry Pi Foundation"));
+
+        // Overrides keep the category of the entry they replace
+        assert!(db.is_virtual(mac("BC:24:11:36:2D:6E")));
+        assert_eq!(name(&db, "BC:24:11:36:2D:6E").as_deref(), Some("Homelab VM"));
     }
 
     #[test]
     fn test_is_virtual() {
         let db = VendorDb::new();
-        assert!(db.is_virtual("BC:24:11:36:2D:6E")); // Proxmox
-        assert!(db.is_virtual("52:54:00:12:34:56")); // QEMU
-        assert!(!db.is_virtual("68:5B:35:8D:89:41")); // Intel (physical)
+        assert!(db.is_virtual(mac("BC:24:11:36:2D:6E"))); // Proxmox
+        assert!(db.is_virtual(mac("52:54:00:12:34:56"))); // QEMU
+        assert!(!db.is_virtual(mac("00:1B:21:8D:89:41"))); // Intel (physical)
+        assert!(db.is_virtual(mac("02:42:AC:11:00:02"))); // Docker
+        // Private client MACs are locally administered too, but not VMs
+        assert!(!db.is_virtual(mac("CA:4E:2B:00:00:01")));
+        assert!(db.is_randomized(mac("CA:4E:2B:00:00:01")));
+        assert!(!db.is_randomized(mac("52:54:00:12:34:56")));
+        assert!(!db.is_randomized(mac("00:1B:21:8D:89:41")));
+    }
+
+    #[test]
+    fn test_describe_formats() {
+        let db = VendorDb::new();
+        for mac in ["bc:24:11:36:2d:6e", "BC-24-11-36-2D-6E", "bc24.1136.2d6e", "BC2411362D6E"] {
+            let report = db.describe(mac).unwrap();
+            assert_eq!(report.mac, "BC:24:11:36:2D:6E");
+            assert_eq!(report.prefix_bits, Some(24));
+            assert_eq!(report.vendor.unwrap().category, VendorCategory::Virtualization);
+            assert!(!report.multicast && !report.local);
+        }
+
+        let report = db.describe("01:00:5e:00:00:fb").unwrap();
+        assert!(report.multicast);
+        assert!(report.vendor.is_none());
+
+        let report = db.describe("da:a1:19:00:00:01").unwrap();
+        assert!(report.local);
+        assert_eq!(report.prefix_bits, None);
+        assert_eq!(report.vendor.unwrap().category, VendorCategory::Randomized);
+
+        assert!(db.describe("bc:24").is_none());
+        assert!(db.describe("not a mac").is_none());
+
+        let report = db.describe("bc 24 11 36 2d 6e").unwrap();
+        assert_eq!(report.mac, "BC:24:11:36:2D:6E");
+        let report = db.describe("bc-24-11").unwrap();
+        assert_eq!(report.mac, "BC:24:11");
+        assert_eq!(report.prefix_bits, Some(24));
+    }
+
+    #[test]
+    fn test_describe_zero_dropped() {
+        let db = VendorDb::new();
+        let report = db.describe("0:1b:21:a:b:c").unwrap();
+        assert_eq!(report.mac, "00:1B:21:0A:0B:0C");
+        assert_eq!(report.vendor.unwrap().name, "Intel Corporate");
+        assert!(!report.multicast && !report.local);
+        assert_eq!(db.describe("0:1b:21").unwrap().mac, "00:1B:21");
+    }
+
+    #[test]
+    fn test_describe_rejects_bad_grouping() {
+        let db = VendorDb::new();
+        for mac in ["aa:bbb:cc", "aa:bb-cc:dd", "001b.21", "00:1b::21", "bc2411362d6e00", "+1b:21:8d"] {
+            assert!(db.describe(mac).is_none(), "{}", mac);
+        }
+    }
+
+    #[test]
+    fn test_builtin_tables_sorted() {
+        for table in [&BUILTIN, &TEST_VENDORS] {
+            assert!(table.ma_l.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert!(table.ma_m.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            assert!(table.ma_s.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        }
+        assert_eq!(BUILTIN.get(0xBC2411, 24).map(|info| info.category), Some(VendorCategory::Virtualization));
+        // Categories derived from the organization names at build time
+        assert_eq!(BUILTIN.get(0x00000C, 24).map(|info| info.category), Some(VendorCategory::Networking));
+        assert_eq!(BUILTIN.get(0xB827EB, 24).map(|info| info.category), Some(VendorCategory::Iot));
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/test_vendors.rs"));
+
+    #[test]
+    fn test_compiled_ma_m_and_ma_s() {
+        // Built from data/test_vendors.csv by the same build step as the built-in table
+        let db = VendorDb::with_builtin(&TEST_VENDORS);
+
+        let report = db.describe("1C:88:79:7A:00:01").unwrap();
+        assert_eq!(report.prefix_bits, Some(28));
+        assert_eq!(report.vendor.unwrap().name, "Sensys Networks, Inc.");
+        let report = db.describe("1C:88:79:1A:00:01").unwrap();
+        assert_eq!(report.prefix_bits, Some(24));
+        assert_eq!(report.vendor.unwrap().name, "IEEE Registration Authority");
+
+        let report = db.describe("70:B3:D5:F2:C1:23").unwrap();
+        assert_eq!(report.prefix_bits, Some(36));
+        assert_eq!(report.vendor.unwrap().name, "Vision Technologies");
+        assert_eq!(db.describe("70:B3:D5:F2:D1:23").unwrap().prefix_bits, Some(24));
     }
 }